@@ -0,0 +1,241 @@
+//! Clojure's own syntax of the thread macros (`-> x f (g a)`), translated to instructions.
+
+use proc_macro2::{Group, Ident, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{quote, ToTokens};
+use syn::{
+    braced, bracketed, parenthesized,
+    parse::{Parse, ParseStream},
+    token::{Brace, Bracket, Paren},
+    AngleBracketedGenericArguments, ExprPath, Lit, Token,
+};
+
+use crate::misc::Placement;
+
+/// The arrows of the Clojure threading macros, with the placement of their steps.
+pub(crate) enum Arrow {
+    /// `->` and `->>`.
+    Thread(Placement),
+    /// `as->`.
+    As,
+    /// `some->` and `some->>`.
+    Some(Placement),
+    /// `cond->` and `cond->>`.
+    Cond(Placement),
+}
+
+impl Arrow {
+    /// Whether the input starts with an arrow rather than the syntax of this crate.
+    pub fn peek(input: ParseStream) -> bool {
+        input.peek(Token![->])
+            || (input.peek(Token![as]) && input.peek2(Token![->]))
+            || (Self::peek_word(input, &["some", "cond"]) && input.peek2(Token![->]))
+    }
+
+    fn peek_word(input: ParseStream, words: &[&str]) -> bool {
+        input
+            .cursor()
+            .ident()
+            .map_or(false, |(ident, _)| words.iter().any(|word| ident == word))
+    }
+
+    /// Parse the `->` or `->>` after the name of an arrow.
+    fn parse_placement(input: ParseStream) -> syn::Result<Placement> {
+        input.parse::<Token![->]>()?;
+        Ok(if input.peek(Token![>]) {
+            input.parse::<Token![>]>()?;
+            Placement::Last
+        } else {
+            Placement::First
+        })
+    }
+}
+
+impl Parse for Arrow {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![as]) {
+            input.parse::<Token![as]>()?;
+            input.parse::<Token![->]>()?;
+            return Ok(Self::As);
+        }
+        if input.peek(Token![->]) {
+            return Ok(Self::Thread(Self::parse_placement(input)?));
+        }
+
+        let name = input.parse::<Ident>()?;
+        let placement = Self::parse_placement(input)?;
+        match name.to_string().as_str() {
+            "some" => Ok(Self::Some(placement)),
+            "cond" => Ok(Self::Cond(placement)),
+            _ => Err(syn::Error::new(
+                name.span(),
+                "expected one of `->`, `->>`, `as->`, `some->`, `some->>`, `cond->` or `cond->>`",
+            )),
+        }
+    }
+}
+
+/// The name bound by `as->`, either an identifier or `$`.
+pub(crate) fn parse_alias(input: ParseStream) -> syn::Result<Ident> {
+    if input.peek(Token![$]) {
+        input.parse::<Token![$]>()?;
+        return Ok(dollar());
+    }
+    input.parse()
+}
+
+/// The hidden name standing for `$`.
+fn dollar() -> Ident {
+    Ident::new("dollar", Span::mixed_site())
+}
+
+/// Translate a form to an expression of Rust.
+///
+/// - Symbols and paths (`f`, `str::len`) and literals are kept as they are.
+/// - Lists are calls (`(f a b)` is `f(a, b)`) or macros (`(vec! 1 2)` is `vec![1, 2]`).
+/// - Lists with a leading dot are methods: `(.m a b)` is `a.m(b)`, or the step `.m(b)`, and take
+///   a turbofish: `(.collect::<Vec<_>>)`.
+/// - Vectors are arrays: `[a b]` is `[a, b]`.
+/// - Braces escape to Rust: `{|v| v + 1}` is `|v| v + 1`.
+/// - `$` is the name of `as->`, `_` is the placeholder and `&` and `-` prefix a form.
+pub(crate) fn parse_form(
+    input: ParseStream,
+    dollar_bound: bool,
+    step: bool,
+) -> syn::Result<TokenStream2> {
+    if input.peek(Paren) {
+        let content;
+        parenthesized!(content in input);
+        return parse_list(&content, dollar_bound, step);
+    }
+    if input.peek(Bracket) {
+        let content;
+        let bracket = bracketed!(content in input);
+        let forms = parse_forms(&content, dollar_bound)?;
+        let mut array = Group::new(proc_macro2::Delimiter::Bracket, quote! { #(#forms),* });
+        array.set_span(bracket.span.join());
+        return Ok(array.into_token_stream());
+    }
+    if input.peek(Brace) {
+        let content;
+        braced!(content in input);
+        let tokens = content.parse::<TokenStream2>()?;
+        return Ok(if dollar_bound {
+            replace_dollars(tokens)
+        } else {
+            tokens
+        });
+    }
+    if input.peek(Token![$]) {
+        let token = input.parse::<Token![$]>()?;
+        if !dollar_bound {
+            return Err(syn::Error::new(
+                token.span,
+                "`$` is only bound with `as-> x $`",
+            ));
+        }
+        return Ok(dollar().into_token_stream());
+    }
+    if input.peek(Token![_]) {
+        return Ok(input.parse::<Token![_]>()?.into_token_stream());
+    }
+    if input.peek(Token![&]) || input.peek(Token![-]) {
+        let prefix = input.parse::<TokenTree>()?;
+        let form = parse_form(input, dollar_bound, false)?;
+        return Ok(quote! { #prefix #form });
+    }
+    if input.peek(Lit) {
+        return Ok(input.parse::<Lit>()?.into_token_stream());
+    }
+    match input.parse::<ExprPath>() {
+        Ok(path) => Ok(path.into_token_stream()),
+        Err(error) => Err(syn::Error::new(
+            error.span(),
+            "expected a form: a symbol, a literal, a list `(f a)`, a vector `[a b]` or Rust in braces `{...}`",
+        )),
+    }
+}
+
+/// Translate the content of a list.
+fn parse_list(input: ParseStream, dollar_bound: bool, step: bool) -> syn::Result<TokenStream2> {
+    if input.is_empty() {
+        return Ok(quote! { () });
+    }
+
+    if input.peek(Token![.]) {
+        let dot = input.parse::<Token![.]>()?;
+        let method = input.parse::<Ident>()?;
+        let turbofish = if input.peek(Token![::]) {
+            let colons = input.parse::<Token![::]>()?;
+            let generics = input.parse::<AngleBracketedGenericArguments>()?;
+            Some(quote! { #colons #generics })
+        } else {
+            None
+        };
+        let mut args = parse_forms(input, dollar_bound)?.into_iter();
+        // the value is the receiver of a step
+        let receiver = if step { None } else { args.next() };
+        let args = args.collect::<Vec<_>>();
+        return Ok(quote! { #receiver #dot #method #turbofish(#(#args),*) });
+    }
+
+    let head = parse_form(input, dollar_bound, false)?;
+    if input.peek(Token![!]) {
+        let bang = input.parse::<Token![!]>()?;
+        let args = parse_forms(input, dollar_bound)?;
+        return Ok(quote! { #head #bang [#(#args),*] });
+    }
+    let args = parse_forms(input, dollar_bound)?;
+    Ok(quote! { #head(#(#args),*) })
+}
+
+/// Translate the forms up to the end of the input.
+fn parse_forms(input: ParseStream, dollar_bound: bool) -> syn::Result<Vec<TokenStream2>> {
+    let mut forms = Vec::new();
+    while !input.is_empty() {
+        forms.push(parse_form(input, dollar_bound, false)?);
+    }
+    Ok(forms)
+}
+
+/// Translate the steps up to the end of the input to comma separated instructions.
+///
+/// A step is a form followed by an optional `?` if it is fallible. With `cond`, every step is
+/// preceded by its test.
+pub(crate) fn parse_steps(
+    input: ParseStream,
+    dollar_bound: bool,
+    cond: bool,
+) -> syn::Result<TokenStream2> {
+    let mut instructions = Vec::new();
+    while !input.is_empty() {
+        let test = if cond {
+            let test = parse_form(input, dollar_bound, false)?;
+            if input.is_empty() {
+                return Err(input.error("expected a form after the test of `cond->`"));
+            }
+            Some(quote! { #test => })
+        } else {
+            None
+        };
+        let form = parse_form(input, dollar_bound, true)?;
+        let try_token = input.parse::<Option<Token![?]>>()?;
+        instructions.push(quote! { #test #form #try_token });
+    }
+    Ok(quote! { #(#instructions),* })
+}
+
+/// Replace the `$` tokens in the Rust tokens of braces with the name of `as->`.
+fn replace_dollars(tokens: TokenStream2) -> TokenStream2 {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Punct(punct) if punct.as_char() == '$' => dollar().into(),
+            TokenTree::Group(group) => {
+                let mut replaced = Group::new(group.delimiter(), replace_dollars(group.stream()));
+                replaced.set_span(group.span());
+                replaced.into()
+            }
+            token => token,
+        })
+        .collect()
+}
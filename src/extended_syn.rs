@@ -1,13 +1,16 @@
 //! Extra helpers missing from the [`syn`] crate.
 
-use proc_macro2::TokenStream as TokenStream2;
+use proc_macro2::{Ident, TokenStream as TokenStream2, TokenTree};
 use quote::{quote, ToTokens};
 use syn::{
     parenthesized,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
     token::Paren,
-    AngleBracketedGenericArguments, Attribute, Expr, ExprCall, ExprMethodCall, Ident, Token,
+    visit_mut::{self, VisitMut},
+    AngleBracketedGenericArguments, Attribute, Expr, ExprAssign, ExprAsync, ExprAwait, ExprCall,
+    ExprClosure, ExprField, ExprInfer, ExprMethodCall, ExprPath, Macro, Member, Pat, PatIdent,
+    PatParen, PatReference, PatSlice, PatStruct, PatTuple, PatTupleStruct, Token,
 };
 
 /// Holds extra tokens.
@@ -25,11 +28,16 @@ pub(crate) mod token {
     }
 
     custom_keyword!(Ok as KwOk);
+    custom_keyword!(Err as KwErr);
     custom_keyword!(Some as KwSome);
     custom_keyword!(Cond as KwCond);
     custom_keyword!(CondClone as KwCondClone);
+    custom_keyword!(CondFirst as KwCondFirst);
+    custom_keyword!(Try as KwTry);
     custom_keyword!(first as KwFirst);
     custom_keyword!(last as KwLast);
+    custom_keyword!(nth as KwNth);
+    custom_keyword!(tap as KwTap);
 }
 
 /// Parse a `(T)`.
@@ -39,26 +47,92 @@ pub(crate) fn parse_required_parens<T: Parse>(input: ParseStream) -> syn::Result
     content.parse()
 }
 
-/// Parse a `(T)` or `T`.
-pub(crate) fn parse_optional_parens<T: Parse>(input: ParseStream) -> syn::Result<T> {
-    if input.peek(Paren) {
-        parse_required_parens(input)
-    } else {
-        input.parse()
-    }
+pub(crate) fn attrs_to_tokens(attrs: &[Attribute]) -> TokenStream2 {
+    attrs.iter().map(|i| i.into_token_stream()).collect()
 }
 
-/// Parse a `(T)` or `T` or only `(T)` based on given flag.
-pub(crate) fn parse_parens<T: Parse>(input: ParseStream, required: bool) -> syn::Result<T> {
-    if required {
-        parse_required_parens(input)
-    } else {
-        parse_optional_parens(input)
-    }
-}
+/// Rebuild a pattern binding everything it matches as the expression it matches.
+///
+/// `(a, [b, c])`, `ref mut a` and `Point { x, y: (a, b) }` are rebuilt, but patterns dropping a
+/// part of the value (`_`, `..`) or only matching it (`1`, `None`) can't be.
+pub(crate) fn pat_to_expr(pat: &Pat) -> syn::Result<TokenStream2> {
+    let elems_to_exprs = |elems: &Punctuated<Pat, Token![,]>| -> syn::Result<TokenStream2> {
+        let mut exprs = Punctuated::<TokenStream2, Token![,]>::new();
+        for pair in elems.pairs() {
+            exprs.push_value(pat_to_expr(pair.value())?);
+            if let Some(comma) = pair.punct() {
+                exprs.push_punct(**comma);
+            }
+        }
+        Ok(exprs.into_token_stream())
+    };
 
-pub(crate) fn attrs_to_tokens(attrs: &Vec<Attribute>) -> TokenStream2 {
-    attrs.iter().map(|i| i.into_token_stream()).collect()
+    Ok(match pat {
+        Pat::Ident(PatIdent {
+            ident,
+            subpat: None,
+            ..
+        }) => ident.into_token_stream(),
+        Pat::Paren(PatParen { pat, .. }) => {
+            let expr = pat_to_expr(pat)?;
+            quote! { (#expr) }
+        }
+        Pat::Reference(PatReference {
+            and_token,
+            mutability,
+            pat,
+            ..
+        }) => {
+            let expr = pat_to_expr(pat)?;
+            quote! { #and_token #mutability #expr }
+        }
+        Pat::Slice(PatSlice { elems, .. }) => {
+            let exprs = elems_to_exprs(elems)?;
+            quote! { [#exprs] }
+        }
+        Pat::Tuple(PatTuple { elems, .. }) => {
+            let exprs = elems_to_exprs(elems)?;
+            quote! { (#exprs) }
+        }
+        Pat::TupleStruct(PatTupleStruct {
+            qself: None,
+            path,
+            elems,
+            ..
+        }) => {
+            let exprs = elems_to_exprs(elems)?;
+            quote! { #path(#exprs) }
+        }
+        Pat::Struct(PatStruct {
+            qself: None,
+            path,
+            fields,
+            rest: None,
+            ..
+        }) => {
+            let fields = fields
+                .iter()
+                .map(|field| {
+                    let member = &field.member;
+                    Ok(match field.colon_token {
+                        Some(colon) => {
+                            let expr = pat_to_expr(&field.pat)?;
+                            quote! { #member #colon #expr }
+                        }
+                        None => member.into_token_stream(),
+                    })
+                })
+                .collect::<syn::Result<Punctuated<TokenStream2, Token![,]>>>()?;
+            quote! { #path { #fields } }
+        }
+        pat => {
+            return Err(syn::Error::new_spanned(
+                pat,
+                "this pattern can't be an alias, an alias must bind all of the value so it can be \
+                 threaded on",
+            ))
+        }
+    })
 }
 
 pub(crate) fn expr_args_to_token_args(
@@ -69,75 +143,238 @@ pub(crate) fn expr_args_to_token_args(
         .collect::<Punctuated<TokenStream2, Token![,]>>()
 }
 
+/// Replaces the placeholders (`_` in expression position) with a value.
+///
+/// Macros are only visited if their arguments are comma separated expressions (like `format!` or
+/// `vec!`) and are not known to take patterns (like `matches!`) whose `_` is a wildcard.
+struct Placeholder<'a> {
+    value: &'a TokenStream2,
+    found: bool,
+}
+
+impl Placeholder<'_> {
+    /// The macros taking patterns which parse as expressions.
+    const PATTERN_MACROS: &'static [&'static str] =
+        &["matches", "assert_matches", "debug_assert_matches"];
+}
+
+impl VisitMut for Placeholder<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let Expr::Infer(_) = expr {
+            *expr = Expr::Verbatim(self.value.clone());
+            self.found = true;
+        } else {
+            visit_mut::visit_expr_mut(self, expr);
+        }
+    }
+
+    /// The left side of a destructuring assignment (`(a, _) = pair`) is a pattern.
+    fn visit_expr_assign_mut(&mut self, assign: &mut ExprAssign) {
+        self.visit_expr_mut(&mut assign.right);
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut Macro) {
+        let is_pattern_macro = mac.path.segments.last().map_or(false, |segment| {
+            Self::PATTERN_MACROS
+                .iter()
+                .any(|name| segment.ident == name)
+        });
+        if is_pattern_macro {
+            return;
+        }
+        let Ok(mut args) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+        else {
+            return;
+        };
+
+        let found_before = self.found;
+        self.found = false;
+        args.iter_mut().for_each(|arg| self.visit_expr_mut(arg));
+        if self.found {
+            mac.tokens = args.into_token_stream();
+        }
+        self.found |= found_before;
+    }
+}
+
+/// Finds the `.await`s of an expression outside of closures and async blocks.
+#[derive(Default)]
+struct Awaits {
+    found: bool,
+}
+
+impl VisitMut for Awaits {
+    fn visit_expr_await_mut(&mut self, _: &mut ExprAwait) {
+        self.found = true;
+    }
+
+    fn visit_expr_async_mut(&mut self, _: &mut ExprAsync) {}
+
+    fn visit_expr_closure_mut(&mut self, _: &mut ExprClosure) {}
+}
+
+/// Finds the uses of any of the names as a variable, including in the tokens of macros.
+struct Mentions<'a> {
+    names: &'a [Ident],
+    found: bool,
+}
+
+impl Mentions<'_> {
+    fn visit_tokens(&mut self, tokens: TokenStream2) {
+        for token in tokens {
+            match token {
+                TokenTree::Ident(ident) => self.found |= self.names.contains(&ident),
+                TokenTree::Group(group) => self.visit_tokens(group.stream()),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl VisitMut for Mentions<'_> {
+    fn visit_expr_path_mut(&mut self, expr: &mut ExprPath) {
+        if let Some(ident) = expr.path.get_ident() {
+            self.found |= self.names.contains(ident);
+        }
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut Macro) {
+        self.visit_tokens(mac.tokens.clone());
+    }
+
+    fn visit_expr_closure_mut(&mut self, closure: &mut ExprClosure) {
+        // the parameters shadow the names in the body
+        let params = closure
+            .inputs
+            .iter()
+            .flat_map(pat_names)
+            .collect::<Vec<_>>();
+        let names = self
+            .names
+            .iter()
+            .filter(|name| !params.contains(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut mentions = Mentions {
+            names: &names,
+            found: false,
+        };
+        mentions.visit_expr_mut(&mut closure.body);
+        self.found |= mentions.found;
+    }
+}
+
+/// The names bound by a pattern.
+#[derive(Default)]
+struct Names(Vec<Ident>);
+
+impl VisitMut for Names {
+    fn visit_pat_ident_mut(&mut self, pat: &mut PatIdent) {
+        self.0.push(pat.ident.clone());
+        visit_mut::visit_pat_ident_mut(self, pat);
+    }
+}
+
+/// Removes the `ref`s of the names of a pattern.
+struct RemoveRefs;
+
+impl VisitMut for RemoveRefs {
+    fn visit_pat_ident_mut(&mut self, pat: &mut PatIdent) {
+        pat.by_ref = None;
+        visit_mut::visit_pat_ident_mut(self, pat);
+    }
+}
+
+/// The pattern binding its names by value: `(a, mut b)` for `(ref a, ref mut b)`.
+pub(crate) fn pat_without_refs(pat: &Pat) -> Pat {
+    let mut pat = pat.clone();
+    RemoveRefs.visit_pat_mut(&mut pat);
+    pat
+}
+
+/// The names bound by a pattern: `a` and `b` in `(a, Point { x: b, .. })`.
+pub(crate) fn pat_names(pat: &Pat) -> Vec<Ident> {
+    let mut names = Names::default();
+    names.visit_pat_mut(&mut pat.clone());
+    names.0
+}
+
 /// Split a callable expression by arguments and body.
 pub(crate) trait SplitArgs {
     /// Return arguments as tokens and arguments in a separate value.
-    fn split_args(&self) -> (TokenStream2, Punctuated<TokenStream2, syn::Token![,]>);
+    ///
+    /// Fails with an error spanning the expression if it can't be a step.
+    fn split_args(&self) -> syn::Result<(TokenStream2, Punctuated<TokenStream2, syn::Token![,]>)>;
 }
 
 macro_rules! default_split_args {
     ($i:path$(,)?) => {
         impl SplitArgs for $i {
-            fn split_args(&self) -> (TokenStream2, Punctuated<TokenStream2, syn::Token![,]>) {
-                (self.to_token_stream(), Default::default())
+            fn split_args(
+                &self,
+            ) -> syn::Result<(TokenStream2, Punctuated<TokenStream2, syn::Token![,]>)> {
+                Ok((self.to_token_stream(), Default::default()))
             }
         }
     };
 }
 
-macro_rules! braced_split_args {
+// parentheses rather than braces, a block starting a statement can't be called
+macro_rules! parenthesized_split_args {
     ($i:path$(,)?) => {
         impl SplitArgs for $i {
-            fn split_args(&self) -> (TokenStream2, Punctuated<TokenStream2, syn::Token![,]>) {
-                (quote! { { #self } }, Default::default())
+            fn split_args(
+                &self,
+            ) -> syn::Result<(TokenStream2, Punctuated<TokenStream2, syn::Token![,]>)> {
+                Ok((quote! { (#self) }, Default::default()))
             }
         }
     };
 }
 
 default_split_args!(syn::ExprArray);
-braced_split_args!(syn::ExprAssign);
+parenthesized_split_args!(syn::ExprAssign);
 default_split_args!(syn::ExprAsync);
 default_split_args!(syn::ExprAwait);
-braced_split_args!(syn::ExprBinary);
+parenthesized_split_args!(syn::ExprBinary);
 default_split_args!(syn::ExprBlock);
-braced_split_args!(syn::ExprBreak);
+parenthesized_split_args!(syn::ExprBreak);
 
 impl SplitArgs for syn::ExprCall {
-    fn split_args(&self) -> (TokenStream2, Punctuated<TokenStream2, syn::Token![,]>) {
+    fn split_args(&self) -> syn::Result<(TokenStream2, Punctuated<TokenStream2, syn::Token![,]>)> {
         let ExprCall {
             attrs, func, args, ..
         } = self;
         let attrs = attrs_to_tokens(attrs);
-        (
+        Ok((
             quote! {
                 #attrs #func
             },
             expr_args_to_token_args(args),
-        )
+        ))
     }
 }
 
-// MOST OF THE SPLITS DON'T MEAN ANYTHING but are implemented anyways just to compile.
+// Most of the splits below are only reached with placeholders, see `ExtendedExpr::kind`.
 
-braced_split_args!(syn::ExprCast);
-braced_split_args!(syn::ExprClosure);
-braced_split_args!(syn::ExprConst);
-braced_split_args!(syn::ExprContinue);
+parenthesized_split_args!(syn::ExprCast);
+parenthesized_split_args!(syn::ExprClosure);
+parenthesized_split_args!(syn::ExprConst);
+parenthesized_split_args!(syn::ExprContinue);
 default_split_args!(syn::ExprField);
-braced_split_args!(syn::ExprForLoop);
-braced_split_args!(syn::ExprGroup);
+parenthesized_split_args!(syn::ExprForLoop);
+parenthesized_split_args!(syn::ExprGroup);
 default_split_args!(syn::ExprIf);
 default_split_args!(syn::ExprIndex);
 default_split_args!(syn::ExprInfer);
-braced_split_args!(syn::ExprLet);
+parenthesized_split_args!(syn::ExprLet);
 default_split_args!(syn::ExprLit);
-braced_split_args!(syn::ExprLoop);
+parenthesized_split_args!(syn::ExprLoop);
 default_split_args!(syn::ExprMacro);
 default_split_args!(syn::ExprMatch);
 
 impl SplitArgs for syn::ExprMethodCall {
-    fn split_args(&self) -> (TokenStream2, Punctuated<TokenStream2, syn::Token![,]>) {
+    fn split_args(&self) -> syn::Result<(TokenStream2, Punctuated<TokenStream2, syn::Token![,]>)> {
         let ExprMethodCall {
             attrs,
             method,
@@ -147,39 +384,39 @@ impl SplitArgs for syn::ExprMethodCall {
             ..
         } = self;
 
-        let attrs = attrs_to_tokens(&attrs);
-        (
-            quote! { #attrs #method . #receiver #turbofish},
+        let attrs = attrs_to_tokens(attrs);
+        Ok((
+            quote! { #attrs #receiver . #method #turbofish },
             expr_args_to_token_args(args),
-        )
+        ))
     }
 }
 
 default_split_args!(syn::ExprParen);
 default_split_args!(syn::ExprPath);
-braced_split_args!(syn::ExprRange);
-braced_split_args!(syn::ExprReference);
+parenthesized_split_args!(syn::ExprRange);
+parenthesized_split_args!(syn::ExprReference);
 default_split_args!(syn::ExprRepeat);
-braced_split_args!(syn::ExprReturn);
+parenthesized_split_args!(syn::ExprReturn);
 default_split_args!(syn::ExprStruct);
-braced_split_args!(syn::ExprTry);
-braced_split_args!(syn::ExprTryBlock);
+parenthesized_split_args!(syn::ExprTry);
+parenthesized_split_args!(syn::ExprTryBlock);
 default_split_args!(syn::ExprTuple);
-braced_split_args!(syn::ExprUnary);
-braced_split_args!(syn::ExprUnsafe);
+parenthesized_split_args!(syn::ExprUnary);
+parenthesized_split_args!(syn::ExprUnsafe);
 
 // Expr::Verbatim
 impl SplitArgs for TokenStream2 {
-    fn split_args(&self) -> (TokenStream2, Punctuated<TokenStream2, syn::Token![,]>) {
-        (self.clone(), Default::default())
+    fn split_args(&self) -> syn::Result<(TokenStream2, Punctuated<TokenStream2, syn::Token![,]>)> {
+        Ok((self.clone(), Default::default()))
     }
 }
 
-braced_split_args!(syn::ExprWhile);
-braced_split_args!(syn::ExprYield);
+parenthesized_split_args!(syn::ExprWhile);
+parenthesized_split_args!(syn::ExprYield);
 
 impl SplitArgs for Expr {
-    fn split_args(&self) -> (TokenStream2, Punctuated<TokenStream2, syn::Token![,]>) {
+    fn split_args(&self) -> syn::Result<(TokenStream2, Punctuated<TokenStream2, syn::Token![,]>)> {
         use Expr::*;
         match self {
             Array(v) => v.split_args(),
@@ -221,79 +458,107 @@ impl SplitArgs for Expr {
             Verbatim(v) => v.split_args(),
             While(v) => v.split_args(),
             Yield(v) => v.split_args(),
-            &_ => todo!("unknown new syntax for syn::Expr"),
+            other => Err(syn::Error::new_spanned(
+                other,
+                format!("unsupported kind of step `{}`", other.to_token_stream()),
+            )),
         }
     }
 }
 
-/// A [`syn::ExprMethodCall`] without the parens and `turbofish` (unable to parse with [`Expr`]).
+/// A method call, field access or `.await` chain missing its receiver: `.trim()`, `.len()`.
+///
+/// The receiver is a placeholder (`_`) so the value is threaded in as the receiver.
 #[derive(Clone)]
-pub(crate) struct TurboMethod {
-    pub attrs: Vec<Attribute>,
-    pub receiver: Expr,
-    pub method: Ident,
-    pub turbofish: AngleBracketedGenericArguments,
+pub(crate) struct MethodChain {
+    pub expr: Expr,
 }
 
-impl Parse for TurboMethod {
+impl Parse for MethodChain {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let v = syn::parse_str::<ExprMethodCall>(&(input.to_string() + "()"))?;
-
-        let Some(turbofish) = v.turbofish else {
-            return Err(syn::Error::new(
-                v.method.span(),
-                "expected `::<...>` after method name",
-            ));
-        };
+        let mut expr = Expr::Infer(ExprInfer {
+            attrs: Vec::new(),
+            underscore_token: Default::default(),
+        });
+
+        loop {
+            let dot_token = input.parse::<Token![.]>()?;
+
+            expr = if input.peek(Token![await]) {
+                Expr::Await(ExprAwait {
+                    attrs: Vec::new(),
+                    base: Box::new(expr),
+                    dot_token,
+                    await_token: input.parse()?,
+                })
+            } else {
+                let member = input.parse::<Member>()?;
+                let turbofish = if input.peek(Token![::]) {
+                    Some(AngleBracketedGenericArguments::parse_turbofish(input)?)
+                } else {
+                    None
+                };
+
+                match member {
+                    Member::Named(method) if turbofish.is_some() || input.peek(Paren) => {
+                        let content;
+                        Expr::MethodCall(ExprMethodCall {
+                            attrs: Vec::new(),
+                            receiver: Box::new(expr),
+                            dot_token,
+                            method,
+                            turbofish,
+                            paren_token: parenthesized!(content in input),
+                            args: content.parse_terminated(Expr::parse, Token![,])?,
+                        })
+                    }
+                    member => Expr::Field(ExprField {
+                        attrs: Vec::new(),
+                        base: Box::new(expr),
+                        dot_token,
+                        member,
+                    }),
+                }
+            };
 
-        Ok(Self {
-            attrs: v.attrs,
-            receiver: *v.receiver,
-            method: v.method,
-            turbofish,
-        })
+            if !input.peek(Token![.]) || input.peek(Token![..]) {
+                return Ok(Self { expr });
+            }
+        }
     }
 }
 
-impl ToTokens for TurboMethod {
+impl ToTokens for MethodChain {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
-        for i in self.attrs.iter() {
-            i.to_tokens(tokens);
-        }
-        self.receiver.to_tokens(tokens);
-        tokens.extend(quote! { . });
-        self.method.to_tokens(tokens);
-        self.turbofish.to_tokens(tokens);
+        self.expr.to_tokens(tokens);
     }
 }
 
-impl SplitArgs for TurboMethod {
-    fn split_args(&self) -> (TokenStream2, Punctuated<TokenStream2, syn::Token![,]>) {
-        let Self {
-            attrs,
-            receiver,
-            method,
-            turbofish,
-        } = self;
-        let attrs = attrs_to_tokens(attrs);
-        (
-            quote! { #attrs #receiver . #method #turbofish },
-            Default::default(),
-        )
+// Always threaded through its placeholder receiver.
+impl SplitArgs for MethodChain {
+    fn split_args(&self) -> syn::Result<(TokenStream2, Punctuated<TokenStream2, syn::Token![,]>)> {
+        Ok((self.to_token_stream(), Default::default()))
     }
 }
 
 /// Extra expressions not defined in [`Expr`].
 #[derive(Clone)]
 pub(crate) enum ExtraExpr {
-    TurboMethod(TurboMethod),
+    MethodChain(MethodChain),
+}
+
+impl ExtraExpr {
+    /// Whether the input starts with one of the extra expressions.
+    fn peek(input: ParseStream) -> bool {
+        input.peek(Token![.]) && !input.peek(Token![..])
+    }
 }
 
 impl Parse for ExtraExpr {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let lookahead = input.lookahead1();
-        if let Ok(turbo) = input.parse() {
-            Ok(Self::TurboMethod(turbo))
+        if lookahead.peek(Token![.]) {
+            Ok(Self::MethodChain(input.parse()?))
         } else {
             Err(lookahead.error())
         }
@@ -303,19 +568,33 @@ impl Parse for ExtraExpr {
 impl ToTokens for ExtraExpr {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         match self {
-            ExtraExpr::TurboMethod(v) => v.to_tokens(tokens),
+            ExtraExpr::MethodChain(v) => v.to_tokens(tokens),
         }
     }
 }
 
 impl SplitArgs for ExtraExpr {
-    fn split_args(&self) -> (TokenStream2, Punctuated<TokenStream2, syn::Token![,]>) {
+    fn split_args(&self) -> syn::Result<(TokenStream2, Punctuated<TokenStream2, syn::Token![,]>)> {
         match self {
-            Self::TurboMethod(v) => v.split_args(),
+            Self::MethodChain(v) => v.split_args(),
         }
     }
 }
 
+/// How the value is threaded into a step without placeholders.
+pub(crate) enum StepKind {
+    /// Called with the value as an argument: `f`, `f(a)`, `a.f()`, `|v| v + 1`.
+    Callable,
+    /// Called with the value as the receiver: `.f()`.
+    Method,
+    /// Only meaningful with placeholders: `_ + 1`, `!_`, `_ as u8`.
+    Operator,
+    /// Never meaningful without placeholders, named for the diagnostics: "a `for` loop".
+    Invalid(&'static str),
+    /// Syntax newer than this crate (`&raw const v`), reported when split.
+    Unknown,
+}
+
 /// The union of [`Expr`] and [`ExtraExpr`].
 #[derive(Clone)]
 pub(crate) enum ExtendedExpr {
@@ -326,15 +605,89 @@ pub(crate) enum ExtendedExpr {
 impl Parse for ExtendedExpr {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         // note that the extra values are the first to be tried.
-        Ok(if let Ok(extra) = input.parse() {
-            Self::Extra(extra)
+        Ok(if ExtraExpr::peek(input) {
+            Self::Extra(input.parse()?)
         } else {
-            // TODO print value options and helpers in the console output if failure occurs
             Self::Expr(input.parse()?)
         })
     }
 }
 
+impl ExtendedExpr {
+    /// Whether the expression awaits (outside of its closures and async blocks).
+    pub fn awaits(&self) -> bool {
+        let mut awaits = Awaits::default();
+        awaits.visit_expr_mut(&mut self.as_expr().clone());
+        awaits.found
+    }
+
+    fn as_expr(&self) -> &Expr {
+        match self {
+            Self::Expr(expr) => expr,
+            Self::Extra(ExtraExpr::MethodChain(chain)) => &chain.expr,
+        }
+    }
+
+    /// Classify the expression as a step.
+    pub fn kind(&self) -> StepKind {
+        use Expr::*;
+        let expr = match self {
+            Self::Extra(ExtraExpr::MethodChain(_)) => return StepKind::Method,
+            Self::Expr(expr) => expr,
+        };
+        match expr {
+            Await(_) | Block(_) | Call(_) | Closure(_) | Field(_) | Group(_) | If(_) | Index(_)
+            | Macro(_) | Match(_) | MethodCall(_) | Paren(_) | Path(_) | Try(_) | Unsafe(_)
+            | Verbatim(_) => StepKind::Callable,
+            Binary(_) | Cast(_) | Range(_) | Reference(_) | Unary(_) => StepKind::Operator,
+            Array(_) => StepKind::Invalid("an array"),
+            Assign(_) => StepKind::Invalid("an assignment"),
+            Async(_) => StepKind::Invalid("an async block"),
+            Break(_) => StepKind::Invalid("a `break`"),
+            Const(_) => StepKind::Invalid("a const block"),
+            Continue(_) => StepKind::Invalid("a `continue`"),
+            ForLoop(_) => StepKind::Invalid("a `for` loop"),
+            Let(_) => StepKind::Invalid("a `let` expression"),
+            Lit(_) => StepKind::Invalid("a literal"),
+            Loop(_) => StepKind::Invalid("a `loop`"),
+            Repeat(_) => StepKind::Invalid("an array repeat"),
+            Return(_) => StepKind::Invalid("a `return`"),
+            Struct(_) => StepKind::Invalid("a struct literal"),
+            TryBlock(_) => StepKind::Invalid("a try block"),
+            Tuple(_) => StepKind::Invalid("a tuple"),
+            While(_) => StepKind::Invalid("a `while` loop"),
+            Yield(_) => StepKind::Invalid("a `yield`"),
+            _ => StepKind::Unknown,
+        }
+    }
+
+    /// Whether the expression uses any of the names as a variable.
+    pub fn mentions(&self, names: &[Ident]) -> bool {
+        let mut mentions = Mentions {
+            names,
+            found: false,
+        };
+        mentions.visit_expr_mut(&mut self.as_expr().clone());
+        mentions.found
+    }
+
+    /// Whether the expression has any placeholders (`_`).
+    pub fn has_placeholders(&self) -> bool {
+        self.replace_placeholders(&TokenStream2::new()).is_some()
+    }
+
+    /// Return the expression with its placeholders (`_`) replaced by `value` if it has any.
+    pub fn replace_placeholders(&self, value: &TokenStream2) -> Option<TokenStream2> {
+        let mut expr = self.as_expr().clone();
+        let mut placeholder = Placeholder {
+            value,
+            found: false,
+        };
+        placeholder.visit_expr_mut(&mut expr);
+        placeholder.found.then(|| expr.into_token_stream())
+    }
+}
+
 impl ToTokens for ExtendedExpr {
     fn to_tokens(&self, tokens: &mut TokenStream2) {
         match self {
@@ -345,7 +698,7 @@ impl ToTokens for ExtendedExpr {
 }
 
 impl SplitArgs for ExtendedExpr {
-    fn split_args(&self) -> (TokenStream2, Punctuated<TokenStream2, syn::Token![,]>) {
+    fn split_args(&self) -> syn::Result<(TokenStream2, Punctuated<TokenStream2, syn::Token![,]>)> {
         match self {
             Self::Extra(v) => v.split_args(),
             Self::Expr(v) => v.split_args(),
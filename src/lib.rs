@@ -2,28 +2,60 @@
 
 // NOTE doto is not written here since Rust syntax makes it obsolete
 
-// TODO add the mut and ref modifier to macro
-
 use proc_macro::TokenStream;
-use syn::parse_macro_input;
+use syn::{parse_macro_input, Item};
 
+mod clojure;
 mod either;
 mod extended_syn;
 mod misc;
+mod pipes;
 mod thread;
 
 use thread::ThreadMacro;
 
-/// The only macro to be used with this crate includes all the other macros.
+/// The main macro of this crate includes all the other macros.
 ///
 /// These macros include:
 /// - The thread-first macro (`->`)
 /// - thread-last (`->>`)
+/// - thread-nth (`x nth(2) in f(a, b)` places `x` as the third argument, Rust only)
 /// - and thread-as (`as->`).
 ///
-/// Also, `some`, `cond` and `ok` (Rust only) are added in the three variants above (`*as` for the
-/// latter three is only in Rust).
+/// Also, `some`, `cond`, `ok` and `err` (Rust only) are added in the variants above (`*as` for the
+/// latter ones is only in Rust). `Try` (Rust only) propagates the result of every step with `?`.
+///
+/// Clojure's own arrows (`-> x f (g a)`, `->>`, `as->`, `some->`, `cond->`) are accepted too, and
+/// so are pipes (`x |> f(a) |>> g(b)`).
 #[proc_macro]
 pub fn thread(tokens: TokenStream) -> TokenStream {
-    parse_macro_input!(tokens with ThreadMacro::generate_tokens)
+    parse_macro_input!(tokens with ThreadMacro::generate_tokens).into()
+}
+
+/// Build a closure taking the value of a pipeline rather than evaluating it.
+///
+/// The preamble is the one of [`thread!`] without the value, which may be replaced by its type:
+/// `pipe_fn!(in f, g)`, `pipe_fn!(_: &str last in f)`, `pipe_fn!(Some(_: Option<i32>) in f)` or
+/// `pipe_fn!(let v: i32 in f(v))`. The steps run from the first to the last, unlike Clojure's `comp`.
+#[proc_macro]
+pub fn pipe_fn(tokens: TokenStream) -> TokenStream {
+    parse_macro_input!(tokens with ThreadMacro::generate_closure_tokens).into()
+}
+
+/// Rewrite the `pipe!(...)` expressions of an item (a function usually) as [`thread!`] does.
+///
+/// `pipe!` takes anything [`thread!`] does, so a function can use `pipe!(x |> f(a) |>> g)`
+/// without importing a macro.
+#[proc_macro_attribute]
+pub fn pipes(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if let Some(token) = proc_macro2::TokenStream::from(attr).into_iter().next() {
+        return syn::Error::new(token.span(), "`pipes` takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let mut item = parse_macro_input!(item as Item);
+    pipes::expand_pipe_macros(&mut item)
+        .map(|()| quote::quote! { #item })
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
@@ -1,14 +1,20 @@
 //! Holds anything else.
 use crate::extended_syn::{
-    parse_parens,
-    token::{KwCond, KwCondClone, KwFirst, KwLast, KwOk, KwSome},
+    parse_required_parens, pat_names, pat_to_expr, pat_without_refs,
+    token::{
+        KwCond, KwCondClone, KwCondFirst, KwErr, KwFirst, KwLast, KwNth, KwOk, KwSome, KwTap, KwTry,
+    },
+    ExtendedExpr, StepKind,
 };
 
-use proc_macro2::TokenStream as TokenStream2;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
 use quote::ToTokens;
 use syn::{
-    parse::{Parse, ParseStream},
-    Expr, Ident, Token,
+    parse::{discouraged::Speculative, Parse, ParseStream},
+    punctuated::Punctuated,
+    Expr, ExprAwait, ExprBinary, ExprCast, ExprClosure, ExprTry, Ident, LitInt, Pat, PatIdent,
+    Token, Type, TypePath,
 };
 
 macro_rules! keyword_enum {
@@ -31,6 +37,11 @@ macro_rules! keyword_enum {
               $vars)*
         }
 
+        impl $name {
+            /// The keywords of the variants.
+            pub const KEYWORDS: &'static [&'static str] = &[stringify!($var)$(, stringify!($vars))*];
+        }
+
         impl Parse for $name {
             fn parse(input: ParseStream) -> syn::Result<Self> {
                 let lookahead = input.lookahead1();
@@ -43,7 +54,7 @@ macro_rules! keyword_enum {
             fn to_tokens(&self, tokens: &mut TokenStream2) {
                 match self {
                     Self::$var => <keyword_enum!(_kw $var)>::default().to_tokens(tokens),
-                    $(Self::$vars => <keyword_enum!(_kw $vars)>::default().to_tokens(tokens))*
+                    $(Self::$vars => <keyword_enum!(_kw $vars)>::default().to_tokens(tokens),)*
                 }
             }
         }
@@ -68,12 +79,107 @@ macro_rules! keyword_enum {
     };
 }
 
+/// The number of single character edits (or swaps of two adjacent ones) between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let substitution = rows[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            row[j] = substitution.min(rows[i - 1][j] + 1).min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// The keyword `ident` is most likely a misspelling of, ignoring the case.
+fn suggest_keyword<'a>(ident: &Ident, keywords: &[&'a str]) -> Option<&'a str> {
+    let ident = ident.to_string().to_lowercase();
+    keywords
+        .iter()
+        .map(|keyword| (edit_distance(&ident, &keyword.to_lowercase()), *keyword))
+        .filter(|(distance, keyword)| *distance <= if keyword.len() <= 4 { 1 } else { 2 })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, keyword)| keyword)
+}
+
+/// An error for an unknown keyword listing the valid `keywords`, suggesting the closest one.
+fn unknown_keyword(ident: &Ident, keywords: &[&str]) -> syn::Error {
+    let expected = keywords
+        .iter()
+        .map(|keyword| format!("`{keyword}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message = match suggest_keyword(ident, keywords) {
+        Some(suggestion) => format!(
+            "unknown thread keyword `{ident}`, did you mean `{suggestion}`? expected one of {expected}"
+        ),
+        None => format!("unknown thread keyword `{ident}`, expected one of {expected}"),
+    };
+    syn::Error::new(ident.span(), message)
+}
+
 keyword_enum! {
     /// Keywords that are processed with map functions with functional utilities.
     #[derive(Debug)]
     Map {
         Some,
         Ok,
+        /// Map the error side of a result.
+        Err,
+    }
+}
+
+impl Map {
+    /// The method used to map a step, flattening the result of fallible steps.
+    pub fn method(&self, fallible: bool) -> TokenStream2 {
+        match (self, fallible) {
+            (Self::Err, false) => quote! { map_err },
+            (Self::Err, true) => quote! { or_else },
+            (_, false) => quote! { map },
+            (_, true) => quote! { and_then },
+        }
+    }
+
+    /// The arms of a `match` equivalent to [`Self::method`] on `binding => call`.
+    ///
+    /// Unlike the closures of the methods, the arms can await.
+    pub fn match_arms(
+        &self,
+        binding: &TokenStream2,
+        call: TokenStream2,
+        fallible: bool,
+    ) -> TokenStream2 {
+        let other = Ident::new("other", Span::mixed_site());
+        let (variant, other_variant) = match self {
+            Self::Some => (
+                quote! { ::core::option::Option::Some },
+                quote! { ::core::option::Option::None },
+            ),
+            Self::Ok => (
+                quote! { ::core::result::Result::Ok },
+                quote! { ::core::result::Result::Err(#other) },
+            ),
+            Self::Err => (
+                quote! { ::core::result::Result::Err },
+                quote! { ::core::result::Result::Ok(#other) },
+            ),
+        };
+        let call = if fallible {
+            call
+        } else {
+            quote! { #variant(#call) }
+        };
+
+        quote! {
+            #variant(#binding) => #call,
+            #other_variant => #other_variant,
+        }
     }
 }
 
@@ -85,20 +191,91 @@ keyword_enum! {
         Cond,
         /// Values that need to be cloned on each use.
         CondClone,
+        /// Only the first matching step is applied, or the `else` one if none match.
+        CondFirst,
     }
 }
 
-keyword_enum! {
-    /// Where an argument must be inserted in a list.
-    Placement {
-        First,
-        Last,
+/// Where an argument must be inserted in a list.
+#[derive(Default)]
+pub(crate) enum Placement {
+    #[default]
+    First,
+    Last,
+    /// Zero-based position of the argument in the list: `nth(2)`.
+    Nth(LitInt),
+}
+
+impl Placement {
+    /// The keywords of the placements.
+    pub const KEYWORDS: &'static [&'static str] = &["first", "last", "nth"];
+
+    /// Insert `new_arg` into `args` at this placement.
+    ///
+    /// Fails if the `Nth` index points past the end of the (already given) arguments.
+    pub fn insert(
+        &self,
+        args: &mut Punctuated<TokenStream2, Token![,]>,
+        new_arg: TokenStream2,
+    ) -> syn::Result<()> {
+        match self {
+            Self::First => args.insert(0, new_arg),
+            Self::Last => args.push(new_arg),
+            Self::Nth(index) => {
+                let position = index.base10_parse::<usize>()?;
+                if position > args.len() {
+                    return Err(syn::Error::new(
+                        index.span(),
+                        format!(
+                            "cannot place the value at argument {position} of a call with {} other argument(s)",
+                            args.len()
+                        ),
+                    ));
+                }
+                args.insert(position, new_arg)
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the input starts with a placement, or an identifier close enough to be a misspelled
+    /// one (`frist`).
+    pub fn peek(input: ParseStream) -> bool {
+        input.cursor().ident().map_or(false, |(ident, _)| {
+            suggest_keyword(&ident, Self::KEYWORDS).is_some()
+        })
     }
 }
 
-impl Default for Placement {
-    fn default() -> Self {
-        Self::First
+impl Parse for Placement {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lookahead = input.lookahead1();
+        if lookahead.peek(KwFirst) {
+            input.parse::<KwFirst>()?;
+            Ok(Self::First)
+        } else if lookahead.peek(KwLast) {
+            input.parse::<KwLast>()?;
+            Ok(Self::Last)
+        } else if lookahead.peek(KwNth) {
+            input.parse::<KwNth>()?;
+            let index = parse_required_parens::<LitInt>(input)?;
+            index.base10_parse::<usize>()?;
+            Ok(Self::Nth(index))
+        } else if input.peek(Ident) {
+            Err(unknown_keyword(&input.parse()?, Self::KEYWORDS))
+        } else {
+            Err(lookahead.error())
+        }
+    }
+}
+
+impl ToTokens for Placement {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        match self {
+            Self::First => KwFirst::default().to_tokens(tokens),
+            Self::Last => KwLast::default().to_tokens(tokens),
+            Self::Nth(index) => tokens.extend(quote! { nth(#index) }),
+        }
     }
 }
 
@@ -106,10 +283,49 @@ impl Default for Placement {
 pub(crate) enum Pattern {
     Map(Map),
     Cond(CondType),
+    /// Propagate the result of every step with `?` rather than mapping.
+    Try,
+}
+
+impl Pattern {
+    /// The keywords of all the patterns.
+    pub fn keywords() -> Vec<&'static str> {
+        [Map::KEYWORDS, CondType::KEYWORDS, &["Try"]].concat()
+    }
+
+    /// Parse a pattern if the input starts with one, failing on what looks like a misspelled one.
+    ///
+    /// An identifier close to a pattern keyword is only taken as a misspelling if the input does
+    /// not parse with `fallback` either, which parses what would follow the pattern (the value or
+    /// the alias). So `Tyr s in` fails but a tuple struct `Ty(1) in` does not.
+    pub fn parse_optional(
+        input: ParseStream,
+        fallback: fn(ParseStream) -> syn::Result<()>,
+    ) -> syn::Result<Option<Self>> {
+        let fork = input.fork();
+        if let Ok(pattern) = fork.parse() {
+            input.advance_to(&fork);
+            return Ok(Some(pattern));
+        }
+
+        let Ok(ident) = input.fork().parse::<Ident>() else {
+            return Ok(None);
+        };
+        let keywords = Self::keywords();
+        if suggest_keyword(&ident, &keywords).is_none() || fallback(&input.fork()).is_ok() {
+            return Ok(None);
+        }
+        Err(unknown_keyword(&ident, &keywords))
+    }
 }
 
 impl Parse for Pattern {
     fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(KwTry) {
+            input.parse::<KwTry>()?;
+            return Ok(Self::Try);
+        }
+
         input
             .parse()
             .map(Pattern::Cond)
@@ -122,24 +338,97 @@ impl ToTokens for Pattern {
         match self {
             Self::Map(v) => v.to_tokens(tokens),
             Self::Cond(v) => v.to_tokens(tokens),
+            Self::Try => KwTry::default().to_tokens(tokens),
         }
     }
 }
 
+/// The name or names the value is bound to: `v`, `(a, b)` or `Point { x, y }`.
+///
+/// The value is bound with the pattern, which is rebuilt as the expression threaded into steps.
+/// The names can have the `mut`, `ref` and `ref mut` modifiers of patterns, the `ref`s only borrow
+/// the initial value.
+#[derive(Clone)]
+pub(crate) struct Alias {
+    pub pat: Pat,
+    /// The pattern rebinding the result of each step, without the `ref`s of `pat` as the results
+    /// are temporaries rather than borrowed values.
+    pub step_pat: Pat,
+    pub expr: TokenStream2,
+}
+
+impl Alias {
+    pub fn new(pat: Pat) -> syn::Result<Self> {
+        let expr = pat_to_expr(&pat)?;
+        let step_pat = pat_without_refs(&pat);
+        Ok(Self {
+            pat,
+            step_pat,
+            expr,
+        })
+    }
+
+    /// The names bound by the alias.
+    pub fn names(&self) -> Vec<Ident> {
+        pat_names(&self.pat)
+    }
+
+    /// The name of the alias unless it destructures the value.
+    pub fn ident(&self) -> Option<&Ident> {
+        match &self.pat {
+            Pat::Ident(PatIdent {
+                ident,
+                subpat: None,
+                ..
+            }) => Some(ident),
+            _ => None,
+        }
+    }
+}
+
+impl From<Ident> for Alias {
+    fn from(ident: Ident) -> Self {
+        let pat = Pat::Ident(PatIdent {
+            attrs: Vec::new(),
+            by_ref: None,
+            mutability: None,
+            ident: ident.clone(),
+            subpat: None,
+        });
+        Self {
+            step_pat: pat.clone(),
+            pat,
+            expr: ident.to_token_stream(),
+        }
+    }
+}
+
+impl Parse for Alias {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Self::new(Pat::parse_single(input)?)
+    }
+}
+
 /// A limited [`LetExpr`] tailored for this crate.
 pub(crate) struct LetAlias {
     /// Keywords for pattern matching behind a let keyword: `let PAT(i) = value`.
     pub pattern: Option<Pattern>,
-    pub alias: Ident,
+    pub alias: Alias,
     pub value: Expr,
 }
 
 impl Parse for LetAlias {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         input.parse::<Token![let]>()?;
-        let pattern = input.parse().ok();
+        let pattern = Pattern::parse_optional(input, |input| {
+            input.parse::<Alias>()?;
+            input.parse::<Token![=]>().map(drop)
+        })?;
 
-        let alias = parse_parens(input, pattern.is_some())?;
+        let alias = match pattern {
+            Some(_) => parse_required_parens::<Alias>(input)?,
+            None => input.parse::<Alias>()?,
+        };
 
         input.parse::<Token![=]>()?;
 
@@ -152,3 +441,224 @@ impl Parse for LetAlias {
         })
     }
 }
+
+/// The condition of a step in the [`CondType`] patterns.
+///
+/// A closure (`|s: &String| s.is_empty()`) is called with a reference to the value, a dot-receiver
+/// (`.is_empty()`) or any placeholder (`_.len() > 3`, `*_ > 3`) gets the reference instead. Any
+/// other expression is used as it is.
+pub(crate) struct Predicate {
+    pub expr: ExtendedExpr,
+}
+
+impl Predicate {
+    /// The condition applied to `reference` (a reference to the value) and whether it was used.
+    pub fn apply(&self, reference: &TokenStream2) -> (TokenStream2, bool) {
+        match &self.expr {
+            ExtendedExpr::Expr(closure @ Expr::Closure(_)) => {
+                (quote! { (#closure)(#reference) }, true)
+            }
+            expr => match expr.replace_placeholders(reference) {
+                Some(condition) => (condition, true),
+                None => (expr.to_token_stream(), false),
+            },
+        }
+    }
+}
+
+impl Parse for Predicate {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(Self {
+            expr: input.parse()?,
+        })
+    }
+}
+
+/// A single instruction of the pipeline with an optional placement overriding the invocation's.
+///
+/// The override is written before the step as an arrow (`-> f(a)` for first and `->> f(a)` for
+/// last) or as a placement followed by a colon (`first: f(a)`, `last: f(a)`, `nth(1): f(a, b)`).
+///
+/// A trailing `?` marks a fallible step: its result is propagated with `?` or, in [`Map`] patterns,
+/// flattened with `and_then`. A trailing `.await` awaits the call after the value is inserted.
+///
+/// A leading `tap` (`tap log`, `tap |v| println!("{v}")`) calls the step with a reference to the
+/// value for its side effects and passes the value itself on.
+///
+/// A trailing `as name` binds the result to `name` for the arguments of the later steps. The value
+/// threaded on is a clone of it, so the result must be `Clone`.
+pub(crate) struct Step {
+    pub tap_token: Option<KwTap>,
+    pub placement: Option<Placement>,
+    pub expr: ExtendedExpr,
+    pub await_token: Option<(Token![.], Token![await])>,
+    pub try_token: Option<Token![?]>,
+    pub binding: Option<Binding>,
+}
+
+/// The `as name` binding of a [`Step`].
+pub(crate) type Binding = (Token![as], Ident);
+
+/// The name of an `as name` binding, telling it apart from a cast (`as u8`, `as Type`).
+///
+/// Any lowercase identifier other than a primitive type is a name, so a cast to a lowercase type
+/// alias ends a step in parentheses: `(_ as c_int)`.
+fn binding_name(ty: &Type) -> Option<&Ident> {
+    const PRIMITIVES: &[&str] = &[
+        "bool", "char", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "str", "u8",
+        "u16", "u32", "u64", "u128", "usize",
+    ];
+
+    let Type::Path(TypePath { qself: None, path }) = ty else {
+        return None;
+    };
+    path.get_ident().filter(|ident| {
+        let name = ident.to_string();
+        name.starts_with(|c: char| c.is_lowercase() || c == '_') && !PRIMITIVES.contains(&&*name)
+    })
+}
+
+/// Take the `as name` binding off a step parsed as a cast by `Expr`.
+///
+/// `as` binds tighter than the binary operators and the body of a closure goes on to the end of
+/// the step, so `v * 3 as n` and `|v| v * 3 as n` are looked for on the right.
+fn take_binding(expr: &mut Expr) -> Option<Binding> {
+    match expr {
+        Expr::Cast(ExprCast {
+            expr: inner,
+            as_token,
+            ty,
+            ..
+        }) => {
+            let binding = (*as_token, binding_name(ty)?.clone());
+            *expr = (**inner).clone();
+            Some(binding)
+        }
+        Expr::Binary(ExprBinary { right, .. }) => take_binding(right),
+        Expr::Closure(ExprClosure { body, .. }) => take_binding(body),
+        _ => None,
+    }
+}
+
+impl Step {
+    /// Parse the optional `tap` keyword of a step.
+    ///
+    /// `tap` alone or followed by anything that can't start a step is a plain function named `tap`.
+    fn parse_tap(input: ParseStream) -> syn::Result<Option<KwTap>> {
+        let starts_step = input.peek2(Ident)
+            || input.peek2(Token![|])
+            || input.peek2(Token![||])
+            || input.peek2(Token![move])
+            || input.peek2(Token![->])
+            || input.peek2(Token![<])
+            || input.peek2(Token![_])
+            || input.peek2(Token![.]) && !input.peek2(Token![..])
+            || input.peek2(syn::token::Brace);
+        if input.peek(KwTap) && starts_step {
+            return Ok(Some(input.parse()?));
+        }
+        Ok(None)
+    }
+
+    /// Parse the optional placement override of a step.
+    fn parse_placement(input: ParseStream) -> syn::Result<Option<Placement>> {
+        if input.peek(Token![->]) {
+            input.parse::<Token![->]>()?;
+            return Ok(Some(if input.peek(Token![>]) {
+                input.parse::<Token![>]>()?;
+                Placement::Last
+            } else {
+                Placement::First
+            }));
+        }
+
+        // `first::f` is a path and `nth(1)` is a call, only the colon makes them a placement.
+        let fork = input.fork();
+        match fork.parse::<Placement>() {
+            Ok(placement) if fork.peek(Token![:]) && !fork.peek(Token![::]) => {
+                fork.parse::<Token![:]>()?;
+                input.advance_to(&fork);
+                Ok(Some(placement))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Reject the steps which the value can't be threaded into, suggesting what would work.
+    pub fn validate(&self) -> syn::Result<()> {
+        if self.expr.has_placeholders() {
+            return Ok(());
+        }
+
+        let message = match self.expr.kind() {
+            StepKind::Callable | StepKind::Method | StepKind::Unknown => return Ok(()),
+            StepKind::Operator => "an operator step needs a placeholder `_` for the value (like \
+                                   `_ + 1`), or wrap it in a closure `|v| ...`"
+                .to_string(),
+            StepKind::Invalid(name) => format!(
+                "{name} can't be a step, use a placeholder `_` for the value in it or wrap it in \
+                 a closure `|v| ...`"
+            ),
+        };
+        Err(syn::Error::new_spanned(&self.expr, message))
+    }
+
+    /// Whether the step has to be awaited, hence can't be in a closure.
+    pub fn awaits(&self) -> bool {
+        self.await_token.is_some() || self.expr.awaits()
+    }
+}
+
+impl Parse for Step {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let tap_token = Self::parse_tap(input)?;
+        let placement = Self::parse_placement(input)?;
+
+        // `f.await?` is parsed as a whole by `Expr` but the suffixes belong to the result of the step.
+        let mut expr = input.parse().map_err(|error| {
+            syn::Error::new(
+                error.span(),
+                format!(
+                    "{error}; a step is a function (`f`), a call (`f(a)`), a method (`.f()`) or \
+                     an expression with a placeholder for the value (`_ + 1`)"
+                ),
+            )
+        })?;
+        let binding = match &mut expr {
+            ExtendedExpr::Expr(expr) => take_binding(expr),
+            ExtendedExpr::Extra(_) => None,
+        };
+        let (expr, try_token) = match expr {
+            ExtendedExpr::Expr(Expr::Try(ExprTry {
+                expr,
+                question_token,
+                ..
+            })) => (ExtendedExpr::Expr(*expr), Some(question_token)),
+            expr if input.peek(Token![?]) => (expr, Some(input.parse()?)),
+            expr => (expr, None),
+        };
+        let (expr, await_token) = match expr {
+            ExtendedExpr::Expr(Expr::Await(ExprAwait {
+                base,
+                dot_token,
+                await_token,
+                ..
+            })) => (ExtendedExpr::Expr(*base), Some((dot_token, await_token))),
+            expr => (expr, None),
+        };
+        // the methods of a chain (`.trim() as t`) are parsed without the cast
+        let binding = match binding {
+            None if input.peek(Token![as]) => Some((input.parse()?, input.parse()?)),
+            binding => binding,
+        };
+
+        Ok(Self {
+            tap_token,
+            placement,
+            expr,
+            await_token,
+            try_token,
+            binding,
+        })
+    }
+}
@@ -0,0 +1,285 @@
+//! The pipe operators (`x |> f(a) |>> g(b)`), translated to instructions.
+
+use proc_macro2::{Spacing, Span, TokenStream as TokenStream2, TokenTree};
+use quote::{quote, ToTokens};
+use syn::{
+    parse::{discouraged::Speculative, ParseStream, Parser},
+    punctuated::Punctuated,
+    visit_mut::{self, VisitMut},
+    Expr, ExprMacro, Item, Macro, Stmt, StmtMacro, Token,
+};
+
+use crate::{
+    misc::{Placement, Predicate, Step},
+    thread::ThreadMacro,
+};
+
+/// A pipe operator between the preamble and a step or two steps.
+enum Pipe {
+    /// `|>` threading the value first.
+    First,
+    /// `|>>` threading the value last.
+    Last,
+}
+
+impl ToTokens for Pipe {
+    /// The placement override of the step after the pipe.
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.extend(match self {
+            Self::First => quote! { -> },
+            Self::Last => quote! { ->> },
+        });
+    }
+}
+
+fn is_punct(token: Option<&TokenTree>, ch: char, spacing: Option<Spacing>) -> bool {
+    match token {
+        Some(TokenTree::Punct(punct)) => {
+            punct.as_char() == ch && spacing.map_or(true, |spacing| punct.spacing() == spacing)
+        }
+        _ => false,
+    }
+}
+
+/// The pipe operator starting at `index`, and the number of its tokens.
+fn pipe_at(tokens: &[TokenTree], index: usize) -> Option<(Pipe, usize)> {
+    let joint = |index: usize, ch: char| is_punct(tokens.get(index), ch, Some(Spacing::Joint));
+    let punct = |index: usize, ch: char| is_punct(tokens.get(index), ch, None);
+
+    if !joint(index, '|') || !punct(index + 1, '>') {
+        return None;
+    }
+    if joint(index + 1, '>') && punct(index + 2, '>') {
+        Some((Pipe::Last, 3))
+    } else {
+        Some((Pipe::First, 2))
+    }
+}
+
+/// Whether the input is a pipeline, having a pipe operator out of any delimiters.
+pub(crate) fn peek(input: ParseStream) -> bool {
+    let tokens = match input.fork().parse::<TokenStream2>() {
+        Ok(tokens) => tokens.into_iter().collect::<Vec<_>>(),
+        Err(_) => return false,
+    };
+    (0..tokens.len()).any(|index| pipe_at(&tokens, index).is_some())
+}
+
+/// Parse the preamble of a pipeline with `parse_value`, failing if it has a placement (`x last`)
+/// since the pipes place every step instead.
+fn parse_preamble(
+    preamble: &TokenStream2,
+    parse_value: impl Fn(ParseStream) -> syn::Result<ThreadMacro>,
+) -> syn::Result<ThreadMacro> {
+    let parser = |input: ParseStream| {
+        let results = parse_value(input)?;
+        if !input.peek(Token![in]) && Placement::peek(input) {
+            return Err(input.error(
+                "a pipeline can't have a placement, `|>` threads the value first and `|>>` last",
+            ));
+        }
+        input.parse::<Token![in]>()?;
+        Ok(results)
+    };
+    parser.parse2(quote! { #preamble in })
+}
+
+/// Parse a single instruction of any pattern: a step, with its predicate or `else` if any.
+fn parse_instruction(input: ParseStream) -> syn::Result<()> {
+    if input.peek(Token![else]) {
+        input.parse::<Token![else]>()?;
+        input.parse::<Token![=>]>()?;
+    } else if input.fork().parse::<Predicate>().is_ok() {
+        let fork = input.fork();
+        fork.parse::<Predicate>()?;
+        if fork.peek(Token![=>]) {
+            input.advance_to(&fork);
+            input.parse::<Token![=>]>()?;
+        }
+    }
+    input.parse::<Step>().map(drop)
+}
+
+/// The instruction of the step after a pipe, placed by the pipe.
+///
+/// The placement goes after the predicate of a `Cond` step (`p => -> f`). Like in the other
+/// grammar, a step using the alias is left as it is (`let v = x |> f(v, 1)`).
+fn translate_step(
+    results: &ThreadMacro,
+    pipe: Pipe,
+    span: Span,
+    step: Vec<TokenTree>,
+) -> syn::Result<TokenStream2> {
+    if step.is_empty() {
+        return Err(syn::Error::new(span, "expected a step after the pipe"));
+    }
+
+    let split = (0..step.len()).find(|&index| {
+        is_punct(step.get(index), '=', Some(Spacing::Joint))
+            && is_punct(step.get(index + 1), '>', None)
+    });
+    let (predicate, expr) = match split {
+        Some(index) => step.split_at(index + 2),
+        None => step.split_at(0),
+    };
+    let (predicate, expr) = (
+        predicate.iter().cloned().collect::<TokenStream2>(),
+        expr.iter().cloned().collect::<TokenStream2>(),
+    );
+
+    let placed = |input: ParseStream| {
+        let fork = input.fork();
+        let placed = input.peek(Token![->])
+            || fork.parse::<Placement>().is_ok() && fork.peek(Token![:]) && !fork.peek(Token![::]);
+        input.parse::<TokenStream2>()?;
+        Ok(placed)
+    };
+    if placed.parse2(expr.clone())? {
+        return Err(syn::Error::new_spanned(
+            expr,
+            "a piped step is placed by its pipe, `|>` threads the value first and `|>>` last",
+        ));
+    }
+
+    let complete =
+        syn::parse2::<Step>(expr.clone()).map_or(false, |step| results.is_complete(&step));
+    let pipe = (!complete).then_some(pipe);
+    let instruction = quote! { #predicate #pipe #expr };
+    // `x |> f, g` would be two steps
+    let comma = step.iter().find(|token| is_punct(Some(token), ',', None));
+    if let Some(comma) = comma {
+        if parse_instruction.parse2(instruction.clone()).is_err() {
+            return Err(syn::Error::new(
+                comma.span(),
+                "the steps of a pipeline are separated by pipes rather than commas",
+            ));
+        }
+    }
+    Ok(instruction)
+}
+
+/// Translate a pipeline to a preamble and its instructions: `x |> f |>> g` is `x in -> f, ->> g`.
+///
+/// The preamble is parsed with `parse_value` as well, to know its alias.
+pub(crate) fn parse_pipeline(
+    input: ParseStream,
+    parse_value: impl Fn(ParseStream) -> syn::Result<ThreadMacro>,
+) -> syn::Result<TokenStream2> {
+    let tokens = input
+        .parse::<TokenStream2>()?
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let mut preamble = Vec::new();
+    let mut steps = Vec::new();
+    let mut index = 0;
+    while index < tokens.len() {
+        match pipe_at(&tokens, index) {
+            Some((pipe, length)) => {
+                steps.push((pipe, tokens[index].span(), Vec::new()));
+                index += length;
+            }
+            None => {
+                let segment = match steps.last_mut() {
+                    Some((_, _, step)) => step,
+                    None => &mut preamble,
+                };
+                segment.push(tokens[index].clone());
+                index += 1;
+            }
+        }
+    }
+
+    if let (true, Some((_, span, _))) = (preamble.is_empty(), steps.first()) {
+        return Err(syn::Error::new(
+            *span,
+            "expected a value before the first pipe",
+        ));
+    }
+    let preamble = preamble.into_iter().collect::<TokenStream2>();
+    let results = parse_preamble(&preamble, parse_value)?;
+    let steps = steps
+        .into_iter()
+        .map(|(pipe, span, step)| translate_step(&results, pipe, span, step))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! { #preamble in #(#steps),* })
+}
+
+/// Replaces the `pipe!(...)` macros with the code of their pipelines, collecting the errors.
+#[derive(Default)]
+struct PipeMacros {
+    error: Option<syn::Error>,
+    expanded: bool,
+}
+
+impl PipeMacros {
+    /// The expression of a `pipe!` macro, or `None` if the macro is not `pipe!`.
+    fn expand(&mut self, mac: &Macro) -> Option<Expr> {
+        if !mac.path.is_ident("pipe") {
+            return None;
+        }
+
+        self.expanded = true;
+        let mut expr = ThreadMacro::generate_tokens
+            .parse2(mac.tokens.clone())
+            .and_then(syn::parse2::<Expr>);
+        match &mut expr {
+            // the pipelines in the arguments of the steps
+            Ok(expr) => self.visit_expr_mut(expr),
+            Err(error) => match &mut self.error {
+                Some(errors) => errors.combine(error.clone()),
+                None => self.error = Some(error.clone()),
+            },
+        }
+        expr.ok()
+    }
+}
+
+impl VisitMut for PipeMacros {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let Expr::Macro(ExprMacro { mac, .. }) = expr {
+            if let Some(expanded) = self.expand(mac) {
+                *expr = expanded;
+                return;
+            }
+        }
+        visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        if let Stmt::Macro(StmtMacro {
+            mac, semi_token, ..
+        }) = stmt
+        {
+            if let Some(expanded) = self.expand(mac) {
+                *stmt = Stmt::Expr(expanded, *semi_token);
+                return;
+            }
+        }
+        visit_mut::visit_stmt_mut(self, stmt);
+    }
+
+    /// The `pipe!`s in the arguments of other macros (`println!`, `vec!`), if they are expressions.
+    fn visit_macro_mut(&mut self, mac: &mut Macro) {
+        let Ok(mut args) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+        else {
+            return;
+        };
+
+        let expanded_before = self.expanded;
+        self.expanded = false;
+        args.iter_mut().for_each(|arg| self.visit_expr_mut(arg));
+        if self.expanded {
+            mac.tokens = args.into_token_stream();
+        }
+        self.expanded |= expanded_before;
+    }
+}
+
+/// Replace the `pipe!(...)` macros in the item with the code of their pipelines.
+pub(crate) fn expand_pipe_macros(item: &mut Item) -> syn::Result<()> {
+    let mut visitor = PipeMacros::default();
+    visitor.visit_item_mut(item);
+    visitor.error.map_or(Ok(()), Err)
+}
@@ -1,174 +1,369 @@
 //! The main struct of the program.
 
-use proc_macro::TokenStream;
-use proc_macro2::TokenStream as TokenStream2;
+use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{quote, ToTokens};
 use syn::{
-    parse::{Parse, ParseStream},
+    parenthesized,
+    parse::{Parse, ParseStream, Parser},
     punctuated::Punctuated,
-    Expr, Ident, Token,
+    spanned::Spanned,
+    Expr, ExprParen, Ident, Pat, Token, Type,
 };
 
 use crate::{
+    clojure::{self, Arrow},
     either::prelude::*,
-    extended_syn::{ExtendedExpr, SplitArgs},
-    misc::{CondType, LetAlias, Pattern, Placement},
+    extended_syn::{parse_required_parens, token::KwTry, ExtendedExpr, SplitArgs},
+    misc::{Alias, CondType, LetAlias, Map, Pattern, Placement, Predicate, Step},
+    pipes,
 };
 
 /// The starting definitions of the a thread macro before the instruction set.
 pub(crate) struct ThreadMacro {
     pattern: Option<Pattern>,
     given_initial_expr: Expr,
-    alias_or_placement: Either<Ident, Placement>,
+    alias_or_placement: Either<Alias, Placement>,
 }
 
 impl ThreadMacro {
     /// Add a new argument to the args list based on the alias or placement.
+    ///
+    /// The `step_placement` of an instruction takes precedence over the invocation's own. With an
+    /// alias, the argument is only added if placed explicitly or if there are no arguments.
     fn add_arg(
         &self,
         args: &mut Punctuated<TokenStream2, Token![,]>,
         new_arg: &TokenStream2,
-        skip_if_placement_and_true: bool,
-    ) {
-        match &self.alias_or_placement {
-            Right(Placement::First) if !skip_if_placement_and_true => {
-                args.insert(0, new_arg.clone())
+        step_placement: Option<&Placement>,
+    ) -> syn::Result<()> {
+        match (&self.alias_or_placement, step_placement) {
+            (_, Some(placement)) | (Right(placement), None) => {
+                placement.insert(args, new_arg.clone())?
             }
-            Right(Placement::Last) if !skip_if_placement_and_true => args.push(new_arg.clone()),
-            Left(alias) if args.is_empty() => args.push(alias.to_token_stream()),
+            (Left(_), None) if args.is_empty() => args.push(new_arg.clone()),
             _ => {} // assumed alias is used correctly in args
         };
+        Ok(())
     }
 
-    /// Resolve which assume alias inputs are valid (if alias used) and don't return output.
+    /// Replace the placeholders of a step with the given value if it has any.
     ///
-    /// In other words, if there are aliases used, assign values to aliases and assume it is
-    /// defined. Else, return the value directly.
-    fn resolve_set_alias(&self, mut instructions: TokenStream2, is_map: bool) -> TokenStream2 {
-        let Either::Left(alias) = &self.alias_or_placement else {
-            return instructions;
-        };
-        let given_initial_expr = &self.given_initial_expr;
+    /// The value is usually a binding of the previous step and is substituted as it is, so the
+    /// result can borrow it (`.trim()`). Any other value (`value.clone()`) is bound once so it is
+    /// evaluated once regardless of the placeholder count.
+    fn fill_placeholders(&self, step: &Step, value: &TokenStream2) -> Option<TokenStream2> {
+        if let (Right(_), Err(_)) = (
+            &self.alias_or_placement,
+            syn::parse2::<Ident>(value.clone()),
+        ) {
+            let placeholder = Ident::new("placeholder", Span::mixed_site()).to_token_stream();
+            let result = step.expr.replace_placeholders(&placeholder)?;
+            return Some(quote! { { let #placeholder = #value; #result } });
+        }
+        step.expr.replace_placeholders(value)
+    }
 
-        if !is_map {
-            instructions.extend(quote! { ; #alias });
+    /// Apply a step to the given value, either through its placeholders or as a new argument.
+    fn call(&self, step: &Step, value: &TokenStream2) -> syn::Result<TokenStream2> {
+        if let Some(result) = self.fill_placeholders(step, value) {
+            return Ok(result);
         }
 
-        quote! {
-            {
-                let #alias = #given_initial_expr;
-                #instructions
-            }
+        if step.placement.is_none() && self.is_complete(step) {
+            return Ok(step.expr.to_token_stream());
         }
+        step.validate()?;
+
+        let (func, mut args) = step.expr.split_args()?;
+        self.add_arg(&mut args, value, step.placement.as_ref())?;
+        Ok(quote! { #func(#args) })
     }
 
-    /// Add an alias to the results or not.
-    fn resolve_instruction_alias(
+    /// Apply a step to the given value like [`Self::call`] and await it if needed.
+    fn call_awaited(&self, step: &Step, value: &TokenStream2) -> syn::Result<TokenStream2> {
+        let call = self.call(step, value)?;
+        Ok(match &step.await_token {
+            Some((dot, await_token)) => quote! { #call #dot #await_token },
+            None => call,
+        })
+    }
+
+    /// Apply a step to the given value with all its suffixes, propagating with `try_token`.
+    ///
+    /// A `tap` step is given a reference to the value and results in the value itself.
+    fn apply(
         &self,
-        given_argument: &TokenStream2,
-        results: TokenStream2,
-    ) -> TokenStream2 {
-        let Left(alias) = &self.alias_or_placement else {
-            return results;
+        step: &Step,
+        value: &TokenStream2,
+        try_token: Option<Token![?]>,
+    ) -> syn::Result<TokenStream2> {
+        if step.tap_token.is_none() {
+            let call = self.call_awaited(step, value)?;
+            return Ok(quote! { #call #try_token });
+        }
+
+        // rebinding a plain alias keeps it usable (and mutable with `mut`) in the step
+        let tapped = match &self.alias_or_placement {
+            Left(alias) if alias.ident().is_some() => alias.clone(),
+            _ => Ident::new("tapped", Span::mixed_site()).into(),
         };
+        let (pat, expr) = (&tapped.step_pat, &tapped.expr);
+        let reference = Ident::new("reference", Span::mixed_site());
+        let call = self.call_awaited(step, &reference.to_token_stream())?;
+        let allow = self.allow_unused_mut();
+        Ok(quote! {
+            {
+                #allow
+                let #pat = #value;
+                let #reference = &#expr;
+                (#call #try_token);
+                #expr
+            }
+        })
+    }
 
-        let results = quote! { let #alias = #results; };
+    fn parse_map_instructions(&self, map: &Map, input: ParseStream) -> syn::Result<TokenStream2> {
+        // the receiver is bound so the initial expression does not mix with the method calls
+        let receiver = Ident::new("receiver", Span::mixed_site());
+        let closure_value = self.value_alias();
+        let (pat, expr) = (&closure_value.pat, &closure_value.expr);
+        let allow = self.allow_unused_mut();
 
-        // to prevent extra semicolons
-        if given_argument.is_empty() {
-            return results;
-        }
-        quote! { #given_argument; #results }
-    }
+        self.parse_instructions(input, Step::parse, &receiver, |last_expr, step| {
+            let fallible = step.try_token.is_some();
+            if let (Some(tap), true) = (&step.tap_token, fallible) {
+                return Err(syn::Error::new(
+                    tap.span,
+                    "a `tap` step can't be fallible in a map pattern",
+                ));
+            }
+            Self::unbound(&step, "the steps of a map pattern run in separate closures")?;
+            let call = self.apply(&step, expr, None)?;
 
-    fn parse_map_instructions(&self, input: ParseStream) -> syn::Result<TokenStream2> {
-        self.parse_instructions(
-            input,
-            ExtendedExpr::parse,
-            |last_expr, expr| {
-                let (func, mut args) = expr.split_args();
-                let no_arg = last_expr.is_empty();
+            // closures can't await
+            if step.awaits() {
+                let arms = map.match_arms(&pat.to_token_stream(), call, fallible);
+                return Ok(quote! { match #last_expr { #allow #arms } });
+            }
 
-                let map_alias = quote! { i };
-                self.add_arg(&mut args, &map_alias, no_arg);
-                let final_alias = self
-                    .alias_or_placement
-                    .left()
-                    .map(ToTokens::to_token_stream)
-                    .unwrap_or(map_alias);
+            let method = map.method(fallible);
+            Ok(quote! { #last_expr.#method(|#allow #pat| #call) })
+        })
+    }
 
-                // If there is an alias passed to the function in `alias_or_placement`, then
-                // necessarily, `given_argument` is empty and must be replaced with that alias.
-                let prefix = if no_arg { &final_alias } else { &last_expr };
+    /// Parse the steps as a `let` statement each, rebinding the value, for plain and `Try`
+    /// pipelines.
+    ///
+    /// With `propagate`, every step is propagated with `?` rather than only the fallible ones.
+    fn parse_let_instructions(
+        &self,
+        input: ParseStream,
+        propagate: bool,
+    ) -> syn::Result<TokenStream2> {
+        let value = self.value_alias();
+        let (pat, step_pat, expr) = (&value.pat, &value.step_pat, &value.expr);
 
-                quote! { #prefix.map(|#final_alias| #func(#args)) }
-            },
-            true,
-        )
-    }
-
-    fn parse_no_map_instructions(&self, input: ParseStream) -> syn::Result<TokenStream2> {
-        self.parse_instructions(
-            input,
-            ExtendedExpr::parse,
-            |last_expr, expr| {
-                let (func, mut args) = expr.split_args();
-                self.add_arg(&mut args, &last_expr, last_expr.is_empty());
-                self.resolve_instruction_alias(&last_expr, quote! { #func(#args) })
-            },
-            false,
-        )
+        let statements = Self::parse_list(input, Step::parse)?
+            .iter()
+            .map(|step| {
+                let try_token = match step.try_token {
+                    None if propagate => Some(Default::default()),
+                    try_token => try_token,
+                };
+                let located_value = self.located_value(&value, step);
+                let result = self.apply(step, &located_value, try_token)?;
+                Ok(self.rebind(step_pat, step, result))
+            })
+            .collect::<syn::Result<TokenStream2>>()?;
+
+        Ok(self.bind_initial_expr(pat, quote! { #statements #expr }))
     }
 
     fn parse_cond_instructions(&self, input: ParseStream) -> syn::Result<TokenStream2> {
-        self.parse_instructions(
-            input,
-            |input| {
-                let cond = input.parse::<Expr>()?;
-                input.parse::<Token![=>]>()?;
-                let expr = input.parse::<ExtendedExpr>()?;
-                Ok((cond, expr))
-            },
-            |mut last_expr, (cond, expr)| {
-                let (func, mut args) = expr.split_args();
-                let no_arg = last_expr.is_empty();
+        let value = self.value_alias();
+        let (pat, step_pat, expr) = (&value.pat, &value.step_pat, &value.expr);
+
+        let statements = Self::parse_list(input, |input| {
+            let predicate = input.parse::<Predicate>()?;
+            input.parse::<Token![=>]>()?;
+            let step = input.parse::<Step>()?;
+            Ok((predicate, step))
+        })?
+        .iter()
+        .map(|(predicate, step)| {
+            let located_value = self.located_value(&value, step);
+            let (reference_binding, cond) = self.test(predicate, &located_value)?;
+
+            let threaded = match &self.pattern {
+                Some(Pattern::Cond(CondType::CondClone)) => quote! { (#located_value.clone()) },
+                _ => located_value,
+            };
+            let result = self.apply(step, &threaded, step.try_token)?;
 
-                if !no_arg {
-                    if let Some(Pattern::Cond(CondType::CondClone)) = &self.pattern {
-                        last_expr = quote! { (#last_expr.clone()) };
+            let result = quote! {
+                {
+                    #reference_binding
+                    if #cond {
+                        #result
+                    } else {
+                        #threaded
                     }
                 }
+            };
+            Ok(self.rebind(step_pat, step, result))
+        })
+        .collect::<syn::Result<TokenStream2>>()?;
+
+        Ok(self.bind_initial_expr(pat, quote! { #statements #expr }))
+    }
+
+    fn parse_cond_first_instructions(&self, input: ParseStream) -> syn::Result<TokenStream2> {
+        let arms = Self::parse_list(input, |input| {
+            let predicate = if input.peek(Token![else]) {
+                Left(input.parse::<Token![else]>()?)
+            } else {
+                Right(input.parse::<Predicate>()?)
+            };
+            input.parse::<Token![=>]>()?;
+            let step = input.parse::<Step>()?;
+            Ok((predicate, step))
+        })?;
 
-                self.add_arg(&mut args, &last_expr, no_arg);
-
-                self.resolve_instruction_alias(
-                    &last_expr,
-                    quote! {
-                        {
-                            if #cond {
-                                #func(#args)
-                            } else {
-                                #last_expr
-                            }
-                        }
-                    },
-                )
+        let value = self.value_alias();
+
+        let mut branches = Vec::new();
+        let mut fallback = None;
+        let mut reference_binding = None;
+        let last_index = arms.len() - 1;
+        for (index, (predicate, step)) in arms.into_iter().enumerate() {
+            Self::unbound(&step, "only one step of `CondFirst` runs")?;
+            let result = self.apply(&step, &value.expr, step.try_token)?;
+            match predicate {
+                Left(else_token) if index != last_index => {
+                    return Err(syn::Error::new(
+                        else_token.span,
+                        "the `else` arm must be the last one",
+                    ))
+                }
+                Left(_) => fallback = Some(result),
+                Right(predicate) => {
+                    let (binding, cond) = self.test(&predicate, &value.expr)?;
+                    reference_binding = reference_binding.or(binding);
+                    branches.push(quote! { if #cond { #result } });
+                }
+            }
+        }
+        let fallback = fallback.unwrap_or_else(|| value.expr.clone());
+
+        Ok(self.bind_initial_expr(
+            &value.pat,
+            quote! {
+                #reference_binding
+                #(#branches else)* { #fallback }
             },
-            false,
-        )
+        ))
     }
 
-    /// Parse a list of instructions. Single source of truth for instruction parsers.
-    fn parse_instructions<T, F>(
+    /// The condition of a predicate on `value` and the binding of the reference it tests if any.
+    fn test(
         &self,
+        predicate: &Predicate,
+        value: &TokenStream2,
+    ) -> syn::Result<(Option<TokenStream2>, TokenStream2)> {
+        let reference = Ident::new("reference", Span::mixed_site());
+        let (cond, uses_value) = predicate.apply(&reference.to_token_stream());
+        if !uses_value {
+            return Ok((None, cond));
+        }
+
+        // a reference to a rebuilt destructuring alias would move its parts
+        match &self.alias_or_placement {
+            Left(alias) if alias.ident().is_none() => Err(syn::Error::new_spanned(
+                &predicate.expr,
+                "a destructuring alias can't be tested by reference, use its names instead",
+            )),
+            _ => Ok((Some(quote! { let #reference = &#value; }), cond)),
+        }
+    }
+
+    /// Rebind the value to `step_pat` after a step, and to the `as` binding of the step if any.
+    fn rebind(&self, step_pat: &Pat, step: &Step, result: TokenStream2) -> TokenStream2 {
+        let allow = self.allow_unused_mut();
+        match &step.binding {
+            // the binding keeps the result so the value is a clone of it
+            Some((_, name)) => quote! {
+                let #name = #result;
+                #allow
+                let #step_pat = ::core::clone::Clone::clone(&#name);
+            },
+            None => quote! { #allow let #step_pat = #result; },
+        }
+    }
+
+    /// Fail if the step has an `as` binding where the later steps can't see it.
+    fn unbound(step: &Step, reason: &str) -> syn::Result<()> {
+        match &step.binding {
+            Some((as_token, name)) => Err(syn::Error::new(
+                as_token.span.join(name.span()).unwrap_or(as_token.span),
+                format!("`as {name}` can't be used here, {reason}"),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Allow an alias to be `mut` even if the value is not mutated in every step.
+    fn allow_unused_mut(&self) -> Option<TokenStream2> {
+        self.alias_or_placement
+            .left()
+            .map(|_| quote! { #[allow(unused_mut)] })
+    }
+
+    /// The names the value is bound to between statements: the alias or a hidden one.
+    fn value_alias(&self) -> Alias {
+        match &self.alias_or_placement {
+            Left(alias) => alias.clone(),
+            Right(_) => Ident::new("value", Span::mixed_site()).into(),
+        }
+    }
+
+    /// Bind the initial expression to `pat` before the statements of a straight-line pipeline.
+    ///
+    /// The binding is a `match` so the temporaries of the initial expression (`s.to_string()` of
+    /// `s.to_string().as_str()`) live through the whole pipeline.
+    fn bind_initial_expr(&self, pat: &Pat, statements: TokenStream2) -> TokenStream2 {
+        let given_initial_expr = &self.given_initial_expr;
+        let allow = self.allow_unused_mut();
+        quote! {
+            match (#given_initial_expr) {
+                #allow
+                #pat => {
+                    #statements
+                }
+            }
+        }
+    }
+
+    /// The hidden value located at the step so the errors about the value point at the step.
+    fn located_value(&self, value: &Alias, step: &Step) -> TokenStream2 {
+        match &self.alias_or_placement {
+            Left(_) => value.expr.clone(),
+            Right(_) => value
+                .expr
+                .clone()
+                .into_iter()
+                .map(|mut token| {
+                    token.set_span(token.span().located_at(step.expr.span()));
+                    token
+                })
+                .collect(),
+        }
+    }
+
+    /// Parse the comma separated list of instructions, failing if there are none.
+    fn parse_list<T>(
         input: ParseStream,
         parser: fn(ParseStream) -> syn::Result<T>,
-        tokenizer: F,
-        is_map: bool,
-    ) -> syn::Result<TokenStream2>
-    where
-        F: FnMut(TokenStream2, T) -> TokenStream2,
-    {
+    ) -> syn::Result<Punctuated<T, Token![,]>> {
         let instructions = Punctuated::<T, Token![,]>::parse_terminated_with(input, parser)?;
 
         if instructions.is_empty() {
@@ -178,47 +373,246 @@ impl ThreadMacro {
             ));
         }
 
-        let expr = instructions.into_iter().fold(
-            // The very first input to the token functions (the actual initial_expr or nothing if alias).
-            match self.alias_or_placement {
-                Left(_) => Default::default(),
-                Right(_) => self.given_initial_expr.to_token_stream(),
-            },
-            tokenizer,
-        );
+        Ok(instructions)
+    }
+
+    /// Parse a list of instructions chained on the `receiver` by the tokenizer.
+    fn parse_instructions<T, F>(
+        &self,
+        input: ParseStream,
+        parser: fn(ParseStream) -> syn::Result<T>,
+        receiver: &Ident,
+        tokenizer: F,
+    ) -> syn::Result<TokenStream2>
+    where
+        F: FnMut(TokenStream2, T) -> syn::Result<TokenStream2>,
+    {
+        let expr = Self::parse_list(input, parser)?
+            .into_iter()
+            .try_fold(receiver.to_token_stream(), tokenizer)?;
 
-        Ok(self.resolve_set_alias(expr, is_map))
+        Ok(self.bind_initial_expr(&Alias::from(receiver.clone()).pat, expr))
     }
 
     /// Parse tokens and generate the valid output.
-    pub fn generate_tokens(input: ParseStream) -> syn::Result<TokenStream> {
+    pub fn generate_tokens(input: ParseStream) -> syn::Result<TokenStream2> {
+        if Arrow::peek(input) {
+            let (results, instructions) = Self::parse_arrow_preamble(input)?;
+            let parser = |input: ParseStream| results.parse_all_instructions(input);
+            return parser.parse2(instructions);
+        }
+        if pipes::peek(input) {
+            let translated = pipes::parse_pipeline(input, Self::parse_value)?;
+            return Self::generate_tokens.parse2(translated);
+        }
+
         let results = Self::parse_preamble(input)?;
 
         input.parse::<Token![in]>()?;
 
-        Ok(match &results.pattern {
-            Some(Pattern::Cond(_)) => results.parse_cond_instructions(input)?,
-            None => results.parse_no_map_instructions(input)?,
-            Some(Pattern::Map(_)) => results.parse_map_instructions(input)?,
+        results.parse_all_instructions(input)
+    }
+
+    /// Parse the instructions after the preamble based on the pattern.
+    fn parse_all_instructions(&self, input: ParseStream) -> syn::Result<TokenStream2> {
+        match &self.pattern {
+            Some(Pattern::Cond(CondType::CondFirst)) => self.parse_cond_first_instructions(input),
+            Some(Pattern::Cond(_)) => self.parse_cond_instructions(input),
+            None => self.parse_let_instructions(input, false),
+            Some(Pattern::Map(map)) => self.parse_map_instructions(map, input),
+            Some(Pattern::Try) => self.parse_let_instructions(input, true),
         }
-        .into())
     }
 
     /// Create an instance by parsing up to `in`.
     pub fn parse_preamble(input: ParseStream) -> syn::Result<Self> {
-        Ok(if input.peek(Token![let]) {
-            input.parse::<LetAlias>()?.into()
-        } else {
-            let pattern = input.parse().ok();
-            let initial_expr = input.parse()?;
-            let placement = input.parse().unwrap_or_default();
+        let mut results = Self::parse_value(input)?;
+        if let (Right(_), false) = (&results.alias_or_placement, input.peek(Token![in])) {
+            results.alias_or_placement = Right(input.parse()?);
+        }
+        Ok(results)
+    }
 
-            Self {
-                pattern,
+    /// Create an instance by parsing the initial value with its pattern or alias, up to the
+    /// placement if any.
+    pub(crate) fn parse_value(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(Token![let]) {
+            return Ok(input.parse::<LetAlias>()?.into());
+        }
+
+        // Keywords are escaped with parentheses (`(Some(v))`) or as raw identifiers (`r#Some(v)`).
+        let pattern = Pattern::parse_optional(input, |input| {
+            input.parse::<Expr>()?;
+            if input.peek(Token![in]) || Placement::peek(input) {
+                Ok(())
+            } else {
+                Err(input.error("expected `in` or a placement"))
+            }
+        })?;
+        // the parens of `Some(...)` are a part of the pattern
+        let initial_expr = match pattern {
+            Some(Pattern::Map(_)) => parse_required_parens(input)?,
+            _ => match input.parse()? {
+                Expr::Paren(ExprParen { expr, .. }) => *expr,
+                expr => expr,
+            },
+        };
+
+        Ok(Self {
+            pattern,
+            given_initial_expr: initial_expr,
+            alias_or_placement: Right(Default::default()),
+        })
+    }
+
+    /// Whether the step is complete as it is, using the alias (or a macro, which may use it
+    /// unseen), so the value is not inserted.
+    pub(crate) fn is_complete(&self, step: &Step) -> bool {
+        self.alias_or_placement.left().map_or(false, |alias| {
+            step.expr.mentions(&alias.names())
+                || matches!(step.expr, ExtendedExpr::Expr(Expr::Macro(_)))
+        })
+    }
+
+    /// Create an instance from one of Clojure's arrows (`-> x f (g a)`, `as-> x $ (f $ 1)`), along
+    /// with its forms translated to the instructions of this crate.
+    pub fn parse_arrow_preamble(input: ParseStream) -> syn::Result<(Self, TokenStream2)> {
+        let arrow = input.parse::<Arrow>()?;
+        let initial_expr = syn::parse2(clojure::parse_form(input, false, false)?)?;
+        let dollar_bound = matches!(arrow, Arrow::As);
+        let cond = matches!(arrow, Arrow::Cond(_));
+
+        let results = match arrow {
+            Arrow::Thread(placement) => Self {
+                pattern: None,
                 given_initial_expr: initial_expr,
                 alias_or_placement: Right(placement),
+            },
+            Arrow::As => LetAlias {
+                pattern: None,
+                alias: clojure::parse_alias(input)?.into(),
+                value: initial_expr,
             }
-        })
+            .into(),
+            Arrow::Some(placement) => Self {
+                pattern: Some(Pattern::Map(Map::Some)),
+                given_initial_expr: initial_expr,
+                alias_or_placement: Right(placement),
+            },
+            Arrow::Cond(placement) => Self {
+                pattern: Some(Pattern::Cond(CondType::Cond)),
+                given_initial_expr: initial_expr,
+                alias_or_placement: Right(placement),
+            },
+        };
+
+        let instructions = clojure::parse_steps(input, dollar_bound, cond)?;
+        Ok((results, instructions))
+    }
+
+    /// Parse tokens without an initial value and generate a closure taking it.
+    pub fn generate_closure_tokens(input: ParseStream) -> syn::Result<TokenStream2> {
+        let argument = Ident::new("argument", Span::mixed_site());
+        if pipes::peek(input) {
+            let translated = pipes::parse_pipeline(input, |input| {
+                Ok(Self::parse_closure_value(input, &argument)?.0)
+            })?;
+            return Self::generate_closure_tokens.parse2(translated);
+        }
+
+        let (results, ty) = Self::parse_closure_preamble(input, &argument)?;
+        input.parse::<Token![in]>()?;
+
+        let ty = ty.map(|ty| quote! { : #ty });
+        let body = results.parse_all_instructions(input)?;
+        Ok(quote! { move |#argument #ty| #body })
+    }
+
+    /// Create an instance by parsing up to `in`, with the `argument` of a closure as the initial
+    /// expression and its optional type.
+    ///
+    /// The value is left out or replaced with its type (`_: &str last`, `Some(_: Option<i32>)`)
+    /// and the alias is not assigned (`let v`, `let Some(v): Option<i32>`).
+    fn parse_closure_preamble(
+        input: ParseStream,
+        argument: &Ident,
+    ) -> syn::Result<(Self, Option<Type>)> {
+        let (mut results, ty) = Self::parse_closure_value(input, argument)?;
+        if let (Right(_), false) = (&results.alias_or_placement, input.peek(Token![in])) {
+            results.alias_or_placement = Right(input.parse()?);
+        }
+        Ok((results, ty))
+    }
+
+    /// Create an instance like [`Self::parse_closure_preamble`], up to the placement if any.
+    fn parse_closure_value(
+        input: ParseStream,
+        argument: &Ident,
+    ) -> syn::Result<(Self, Option<Type>)> {
+        let given_initial_expr = syn::parse_quote! { #argument };
+
+        if input.peek(Token![let]) {
+            input.parse::<Token![let]>()?;
+            let pattern = Pattern::parse_optional(input, |input| {
+                input.parse::<Alias>()?;
+                if input.peek(Token![:]) || input.peek(Token![in]) {
+                    Ok(())
+                } else {
+                    Err(input.error("expected `in` or the type of the argument"))
+                }
+            })?;
+            let alias = match pattern {
+                Some(_) => parse_required_parens(input)?,
+                None => input.parse()?,
+            };
+            let ty = Self::parse_argument_type(input)?;
+            let results = LetAlias {
+                pattern,
+                alias,
+                value: given_initial_expr,
+            };
+            return Ok((results.into(), ty));
+        }
+
+        if input.peek(KwTry) {
+            return Err(input.error(
+                "a closure can't propagate every step with `Try`, mark the fallible steps with `?` \
+                 and wrap the value in a last step (`Ok`) instead",
+            ));
+        }
+        // only a placement can start with an identifier here
+        let pattern = Pattern::parse_optional(input, |input| input.parse::<Placement>().map(drop))?;
+        let ty = if input.peek(syn::token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            let ty = Self::parse_argument_type(&content)?;
+            if !content.is_empty() {
+                return Err(content.error("expected `_` or `_: Type` for the argument"));
+            }
+            ty
+        } else {
+            Self::parse_argument_type(input)?
+        };
+
+        let results = Self {
+            pattern,
+            given_initial_expr,
+            alias_or_placement: Right(Default::default()),
+        };
+        Ok((results, ty))
+    }
+
+    /// Parse the optional type of the argument of a closure: `_`, `_: Type` or `: Type` of an
+    /// alias.
+    fn parse_argument_type(input: ParseStream) -> syn::Result<Option<Type>> {
+        if input.peek(Token![_]) {
+            input.parse::<Token![_]>()?;
+        }
+        if input.peek(Token![:]) && !input.peek(Token![::]) {
+            input.parse::<Token![:]>()?;
+            return Ok(Some(input.parse()?));
+        }
+        Ok(None)
     }
 }
 
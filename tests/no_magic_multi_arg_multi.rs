@@ -58,3 +58,16 @@ fn test_single_instruction_multi_arg_as() {
         "o".to_string(),
     );
 }
+
+#[test]
+fn test_single_instruction_multi_arg_nth() {
+    let x = "hello";
+
+    assert_eq!(
+        thread::thread!(x nth(2) in
+                        third_input_value((), (), (), (), ()),
+                        third_input_value((), (), (), (), ()),
+        ),
+        "hello".to_string(),
+    );
+}
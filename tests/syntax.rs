@@ -6,6 +6,22 @@ fn only_last(_: (), s: &str) -> String {
     s.to_string()
 }
 
+fn only_middle(_: (), s: &str, _: ()) -> String {
+    s.to_string()
+}
+
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+fn sub(a: i32, b: i32) -> i32 {
+    a - b
+}
+
+fn half(v: i32) -> Option<i32> {
+    (v % 2 == 0).then_some(v / 2)
+}
+
 #[cfg(test)]
 mod initial_expr {
     #[test]
@@ -28,7 +44,7 @@ mod initial_expr {
 
 #[cfg(test)]
 mod keywords {
-    use super::only_first;
+    use super::{only_first, only_middle};
 
     #[test]
     fn first() {
@@ -54,6 +70,26 @@ mod keywords {
         );
     }
 
+    #[test]
+    fn nth() {
+        assert_eq!(
+            thread::thread!("hello" nth(1) in only_middle((), ())),
+            "hello".to_string(),
+        );
+    }
+
+    #[test]
+    fn nth_bounds() {
+        assert_eq!(
+            thread::thread!(() nth(0) in only_middle("hello", ())),
+            "hello".to_string(),
+        );
+        assert_eq!(
+            thread::thread!(() nth(2) in only_middle((), "hello")),
+            "hello".to_string(),
+        );
+    }
+
     // #[test]
     // fn as_magic() {
     //     assert_eq!(
@@ -61,6 +97,84 @@ mod keywords {
     //         "hello".to_string(),
     //     );
     // }
+
+    #[test]
+    fn close_to_keywords() {
+        #[derive(Debug, PartialEq)]
+        struct Sum(i32);
+        let ok = 1;
+        let om = 2;
+        assert_eq!(thread::thread!(ok first in std::ops::Add::add(1)), 2);
+        assert_eq!(thread::thread!(om last in std::ops::Sub::sub(3)), 1);
+        assert_eq!(thread::thread!(Sum(1) in |s: Sum| s.0), 1);
+    }
+
+    #[test]
+    fn tuple_structs_close_to_keywords() {
+        struct Home(i32);
+        struct Cont(i32);
+        struct Ty(i32);
+        assert_eq!(thread::thread!(Home(1) in |h: Home| h.0), 1);
+        assert_eq!(thread::thread!(Cont(1) last in |c: Cont| c.0), 1);
+        assert_eq!(thread::thread!(Ty(1) in |t: Ty| t.0), 1);
+        let f = thread::pipe_fn!(let Home(v): Home in Home(v + 1));
+        assert_eq!(f(Home(1)).0, 2);
+    }
+}
+
+/// Values colliding with the keywords of the preamble are escaped with parentheses or as raw
+/// identifiers.
+#[cfg(test)]
+mod escapes {
+    #[test]
+    fn parenthesized() {
+        assert_eq!(thread::thread!((Some(1)) in Option::unwrap), 1);
+        assert_eq!(thread::thread!((Ok::<_, ()>(1)) in Result::unwrap), 1);
+        assert_eq!(thread::thread!((Err::<(), _>(1)) in Result::unwrap_err), 1);
+        assert!(thread::thread!((Some(1)) in .is_some()));
+    }
+
+    #[test]
+    fn raw_identifiers() {
+        assert_eq!(thread::thread!(r#Some(1) in Option::unwrap), 1);
+        assert_eq!(thread::thread!(r#Ok::<_, ()>(1) last in Result::unwrap), 1);
+    }
+
+    #[test]
+    fn pattern_named_values() {
+        #[derive(Debug, PartialEq)]
+        struct Cond(i32);
+        #[allow(non_upper_case_globals)]
+        const Try: i32 = 1;
+
+        assert_eq!(thread::thread!((Cond(1)) in |c: Cond| c.0), 1);
+        assert_eq!(thread::thread!(r#Cond(1) in |c: Cond| c.0), 1);
+        assert_eq!(thread::thread!(r#Try in _ + 1), 2);
+        assert_eq!(thread::thread!((Try) in _ + 1), 2);
+    }
+
+    #[test]
+    fn placement_named_values() {
+        let (first, last, nth, tap) = (1, 2, 3, 4);
+        assert_eq!(thread::thread!(first in _ + 1), 2);
+        assert_eq!(thread::thread!(last last in _ + 1), 3);
+        assert_eq!(thread::thread!(nth first in _ + 1), 4);
+        assert_eq!(thread::thread!(tap in _ + 1), 5);
+    }
+
+    #[test]
+    fn placement_named_steps() {
+        fn first(v: i32) -> i32 {
+            v + 1
+        }
+        fn last(v: i32) -> i32 {
+            v * 2
+        }
+        fn nth(v: i32, w: i32) -> i32 {
+            v - w
+        }
+        assert_eq!(thread::thread!(1 in first, last, nth(1), first: nth(1)), 2);
+    }
 }
 
 #[cfg(test)]
@@ -68,7 +182,7 @@ mod magic {
 
     #[cfg(test)]
     mod some {
-        use crate::{only_first, only_last};
+        use crate::{only_first, only_last, only_middle};
 
         #[test]
         fn first() {
@@ -103,6 +217,20 @@ mod magic {
             assert_eq!(thread::thread!(Some(None) last in only_last(())), None);
         }
 
+        #[test]
+        fn nth() {
+            let v = Some("hello");
+            assert_eq!(
+                thread::thread!(Some(v) nth(1) in only_middle((), ())),
+                Some("hello".to_string()),
+            );
+
+            assert_eq!(
+                thread::thread!(Some(None) nth(1) in only_middle((), ())),
+                None
+            );
+        }
+
         #[test]
         fn as_magic() {
             // let v = Some("hello");
@@ -114,6 +242,1486 @@ mod magic {
             assert_eq!(thread::thread!(Some(None) last in only_last(())), None);
         }
     }
+
+    #[cfg(test)]
+    mod err {
+        fn recover(e: String) -> Result<i32, String> {
+            e.parse().map_err(|_| format!("cannot recover from {e}"))
+        }
+
+        #[test]
+        fn map_err() {
+            let r: Result<i32, &str> = Err("bad");
+            assert_eq!(
+                thread::thread!(Err(r) in .to_uppercase(), format!("error: {}", _)),
+                Err("error: BAD".to_string()),
+            );
+
+            let r: Result<i32, &str> = Ok(1);
+            assert_eq!(thread::thread!(Err(r) in .to_uppercase()), Ok(1));
+        }
+
+        #[test]
+        fn or_else() {
+            let r: Result<i32, &str> = Err("1");
+            assert_eq!(
+                thread::thread!(Err(r) in ToString::to_string, recover?),
+                Ok(1)
+            );
+
+            let r: Result<i32, &str> = Err("bad");
+            assert_eq!(
+                thread::thread!(Err(r) in ToString::to_string, recover?, .len()),
+                Err(23),
+            );
+        }
+
+        #[test]
+        fn last() {
+            let r: Result<(), &str> = Err("good");
+            assert_eq!(
+                thread::thread!(Err(r) last in str::replace("very bad", "bad")),
+                Err("very good".to_string()),
+            );
+        }
+
+        #[test]
+        fn as_magic() {
+            let r: Result<(), &str> = Err("bad");
+            assert_eq!(
+                thread::thread!(let Err(e) = r in str::to_uppercase(e), String::len(&e)),
+                Err(3),
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod try_magic {
+        #[derive(Debug, PartialEq)]
+        struct Error(String);
+
+        impl From<std::num::ParseIntError> for Error {
+            fn from(value: std::num::ParseIntError) -> Self {
+                Self(value.to_string())
+            }
+        }
+
+        fn positive(v: i32) -> Result<i32, Error> {
+            if v > 0 {
+                Ok(v)
+            } else {
+                Err(Error(format!("{v} is not positive")))
+            }
+        }
+
+        fn subtract(a: i32, b: i32) -> Result<i32, Error> {
+            positive(a - b)
+        }
+
+        fn first(s: &str) -> Result<i32, Error> {
+            Ok(thread::thread!(Try s in str::parse::<i32>, positive, subtract(1)))
+        }
+
+        fn last(s: &str) -> Result<i32, Error> {
+            Ok(thread::thread!(Try s last in str::parse::<i32>, positive, subtract(10)))
+        }
+
+        fn as_magic(s: &str) -> Result<i32, Error> {
+            Ok(
+                thread::thread!(let Try(v) = s in str::parse::<i32>(v), subtract(v, 1), subtract(10, v)),
+            )
+        }
+
+        #[test]
+        fn first_placement() {
+            assert_eq!(first("5"), Ok(4));
+            assert_eq!(first("1"), Err(Error("0 is not positive".to_string())));
+            assert_eq!(first("-5"), Err(Error("-5 is not positive".to_string())));
+            assert!(first("five").is_err());
+        }
+
+        #[test]
+        fn last_placement() {
+            assert_eq!(last("5"), Ok(5));
+            assert!(last("11").is_err());
+        }
+
+        #[test]
+        fn as_placement() {
+            assert_eq!(as_magic("5"), Ok(6));
+            assert!(as_magic("1").is_err());
+        }
+
+        #[test]
+        fn option() {
+            fn run(v: &[i32]) -> Option<i32> {
+                Some(thread::thread!(Try v in .first(), .checked_mul(2)))
+            }
+
+            assert_eq!(run(&[2]), Some(4));
+            assert_eq!(run(&[]), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod cond {
+    fn middle(_: (), s: &str, _: ()) -> &str {
+        s.split_once('l').unwrap().1
+    }
+
+    #[test]
+    fn nth() {
+        assert_eq!(
+            thread::thread!(Cond "hello" nth(1) in true => middle((), ()), false => middle((), ())),
+            "lo",
+        );
+    }
+
+    #[test]
+    fn predicate_closure() {
+        let default_text = |_: String| "default".to_string();
+        assert_eq!(
+            thread::thread!(Cond String::new() in |s: &String| s.is_empty() => default_text),
+            "default"
+        );
+        assert_eq!(
+            thread::thread!(Cond "text".to_string() in |s: &String| s.is_empty() => default_text),
+            "text"
+        );
+    }
+
+    #[test]
+    fn predicate_method() {
+        let default_text = |_: String| "default".to_string();
+        assert_eq!(
+            thread::thread!(Cond String::new() in .is_empty() => default_text, .is_empty() => _ + "!"),
+            "default"
+        );
+    }
+
+    #[test]
+    fn predicate_placeholder() {
+        assert_eq!(
+            thread::thread!(Cond 5i32 in *_ > 3 => _ * 2, *_ > 100 => _ - 100, _.is_positive() => _ + 1),
+            11
+        );
+        assert_eq!(
+            thread::thread!(CondClone vec![1, 2] in _.len() > 1 => .into_iter().rev().collect::<Vec<_>>()),
+            [2, 1]
+        );
+    }
+
+    #[test]
+    fn predicate_alias() {
+        assert_eq!(
+            thread::thread!(let Cond(v) = 5 in v > 3 => _ * 2, *_ > 100 => _ - 100, v > 3 => _ + 1),
+            11
+        );
+    }
+
+    #[test]
+    fn plain_condition() {
+        let debug = false;
+        assert_eq!(
+            thread::thread!(Cond 5 in debug => _ * 2, !debug => _ + 1),
+            6
+        );
+    }
+    fn sign(v: i32) -> &'static str {
+        thread::thread!(CondFirst v in
+                        *_ < 0 => |_| "negative",
+                        *_ == 0 => |_| "zero",
+                        else => |_| "positive")
+    }
+
+    #[test]
+    fn first_match() {
+        assert_eq!(sign(-3), "negative");
+        assert_eq!(sign(0), "zero");
+        assert_eq!(sign(3), "positive");
+        assert_eq!(
+            thread::thread!(CondFirst 5 in *_ > 1 => _ * 2, *_ > 2 => _ * 3),
+            10
+        );
+    }
+
+    #[test]
+    fn first_match_without_else() {
+        assert_eq!(thread::thread!(CondFirst 5 in *_ > 10 => _ * 2), 5);
+    }
+
+    #[test]
+    fn first_match_moves() {
+        let text =
+            |s: String| thread::thread!(CondFirst s in .is_empty() => |_| None, else => Some);
+        assert_eq!(text(String::new()), None);
+        assert_eq!(text("a".to_string()), Some("a".to_string()));
+    }
+
+    #[test]
+    fn first_match_alias() {
+        assert_eq!(
+            thread::thread!(let CondFirst(v) = 5 in v > 10 => _ - 10, v > 3 => _ - 3, else => _ + 1),
+            2
+        );
+    }
+
+    #[test]
+    fn first_match_placement() {
+        assert_eq!(
+            thread::thread!(CondFirst "hello" nth(1) in false => middle((), ()), else => middle((), ())),
+            "lo",
+        );
+    }
+    #[test]
+    fn steps_run_once() {
+        use std::cell::Cell;
+
+        let calls = Cell::new(0);
+        let tests = Cell::new(0);
+        let step = |v: i32| {
+            calls.set(calls.get() + 1);
+            v + 1
+        };
+        let test = |v: &i32| {
+            tests.set(tests.get() + 1);
+            *v % 2 == 0
+        };
+
+        assert_eq!(
+            thread::thread!(Cond 0 in
+                            test(_) => step, test(_) => step, test(_) => step, test(_) => step, test(_) => step,
+                            test(_) => step, test(_) => step, test(_) => step, test(_) => step, test(_) => step),
+            1
+        );
+        assert_eq!((calls.get(), tests.get()), (1, 10));
+
+        calls.set(0);
+        assert_eq!(
+            thread::thread!(Cond step(0) in
+                            true => step, true => step, true => step, true => step, true => step,
+                            true => step, true => step, true => step, true => step, true => step),
+            11
+        );
+        assert_eq!(calls.get(), 11);
+
+        calls.set(0);
+        assert_eq!(
+            thread::thread!(CondClone step(0) in true => step, false => step, true => step),
+            3
+        );
+        assert_eq!(calls.get(), 3);
+    }
+}
+
+#[cfg(test)]
+mod step_placement {
+    fn keep_first(s: &str, _: ()) -> &str {
+        s
+    }
+
+    fn keep_last(_: (), s: &str) -> &str {
+        s
+    }
+
+    fn keep_middle(_: (), s: &str, _: ()) -> &str {
+        s
+    }
+
+    #[test]
+    fn arrows() {
+        assert_eq!(
+            thread::thread!("hello" in keep_first(()), ->> keep_last(()), -> keep_first(())),
+            "hello",
+        );
+        assert_eq!(
+            thread::thread!("hello" last in -> keep_first(()), keep_last(())),
+            "hello",
+        );
+    }
+
+    #[test]
+    fn keywords() {
+        assert_eq!(
+            thread::thread!("hello" in last: keep_last(()), nth(1): keep_middle((), ()), first: keep_first(())),
+            "hello",
+        );
+    }
+
+    #[test]
+    fn paths_are_not_placements() {
+        mod first {
+            pub fn f(s: &str) -> &str {
+                s
+            }
+        }
+
+        assert_eq!(thread::thread!("hello" last in first::f), "hello");
+    }
+
+    #[test]
+    fn some() {
+        assert_eq!(
+            thread::thread!(Some(Some("hello")) in ->> keep_last(()), -> keep_first(())),
+            Some("hello"),
+        );
+    }
+
+    #[test]
+    fn cond() {
+        assert_eq!(
+            thread::thread!(Cond "hello" in true => ->> keep_last(()), true => keep_first(())),
+            "hello",
+        );
+    }
+
+    #[test]
+    fn alias() {
+        assert_eq!(
+            thread::thread!(let a = "hello" in ->> keep_last(()), keep_first(a, ())),
+            "hello",
+        );
+    }
+}
+
+#[cfg(test)]
+mod placeholder {
+    use super::add;
+
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(thread::thread!(1 in _ + 1, _ * 10, _ as u64), 20u64);
+        assert_eq!(thread::thread!(2 in -_, [_, 0]), [-2, 0]);
+    }
+
+    #[test]
+    fn nested() {
+        assert_eq!(thread::thread!(1 in add(10, add(_, 100))), 111);
+        assert_eq!(thread::thread!(1 in add(_, _)), 2);
+    }
+
+    #[test]
+    fn struct_literal() {
+        let p = thread::thread!(3 in Point { x: _, y: _ * 2 });
+        assert_eq!((p.x, p.y), (3, 6));
+    }
+
+    #[test]
+    fn macro_args() {
+        assert_eq!(
+            thread::thread!("hello" in format!("{}, {}", _, _)),
+            "hello, hello".to_string()
+        );
+        assert_eq!(thread::thread!(1 in vec![_, _ + 1]), vec![1, 2]);
+    }
+
+    #[test]
+    fn pattern_macros_are_untouched() {
+        fn count(v: i32, matched: bool) -> i32 {
+            v + i32::from(matched)
+        }
+        let p = (1, 2);
+        assert_eq!(thread::thread!(1 in count(matches!(p, (1, _)))), 2);
+        assert_eq!(thread::thread!(1 in count(_, matches!(p, (1, _)))), 2);
+        assert_eq!(thread::thread!(Cond 1 in matches!(p, (_, _)) => add(1)), 2);
+    }
+
+    #[test]
+    fn assignment_wildcards_are_untouched() {
+        assert_eq!(
+            thread::thread!(1 in |v: i32| {
+                let x;
+                (x, _) = (v, 2);
+                x + 1
+            }),
+            2
+        );
+        assert_eq!(
+            thread::thread!(1 in {
+                let x;
+                (x, _) = (_ + 1, 0);
+                x
+            }),
+            2
+        );
+    }
+
+    #[test]
+    fn type_placeholders_are_untouched() {
+        assert_eq!(
+            thread::thread!([1, 2] in _.iter().map(|_| 0).collect::<Vec<_>>()),
+            vec![0, 0]
+        );
+    }
+
+    #[test]
+    fn evaluated_once() {
+        let mut count = 0;
+        let mut next = || {
+            count += 1;
+            count
+        };
+        assert_eq!(thread::thread!(next() in add(_, _), add(_, _)), 4);
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn mixed_with_placement() {
+        assert_eq!(thread::thread!(1 last in _ + 1, add(10)), 12);
+    }
+
+    #[test]
+    fn some() {
+        assert_eq!(thread::thread!(Some(Some(1)) in _ + 1, add(_, _)), Some(4));
+        assert_eq!(thread::thread!(Some(None::<i32>) in _ + 1), None);
+    }
+
+    #[test]
+    fn cond() {
+        assert_eq!(
+            thread::thread!(Cond 1 in true => _ + 1, false => _ * 100),
+            2
+        );
+    }
+
+    #[test]
+    fn alias() {
+        assert_eq!(thread::thread!(let a = 1 in _ + 1, add(a, _)), 4);
+    }
+}
+
+#[cfg(test)]
+mod method {
+    #[test]
+    fn receiver_first() {
+        assert_eq!(
+            thread::thread!(" a,b " first in .trim(), .split(',').count()),
+            2
+        );
+    }
+
+    #[test]
+    fn receiver_last() {
+        assert_eq!(
+            thread::thread!(" a,b " last in .trim(), .to_uppercase()),
+            "A,B".to_string()
+        );
+    }
+
+    #[test]
+    fn receiver_owned() {
+        let s = String::from(" a ");
+        assert_eq!(thread::thread!(s in .trim(), str::len), 1);
+        let v = vec![1, 2];
+        assert_eq!(thread::thread!(v in .iter(), Iterator::sum::<i32>), 3);
+        assert_eq!(
+            thread::thread!(String::from("b ") in .trim_end(), _.len()),
+            1
+        );
+    }
+
+    #[test]
+    fn receiver_turbofish() {
+        assert_eq!(
+            thread::thread!("1,2" in .split(','), .map(str::parse::<i32>), .collect::<Result<Vec<_>, _>>()),
+            Ok(vec![1, 2])
+        );
+    }
+
+    #[test]
+    fn receiver_field() {
+        assert_eq!(thread::thread!((1, "hello") in .1, .len()), 5);
+    }
+
+    #[test]
+    fn receiver_as() {
+        assert_eq!(thread::thread!(let s = " hi " in .trim(), .len()), 2);
+    }
+
+    #[test]
+    fn receiver_some() {
+        assert_eq!(
+            thread::thread!(Some(Some(" hi ")) in .trim(), .len()),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn args_first() {
+        assert_eq!(
+            thread::thread!("l" first in "hello".replace("L")),
+            "heLLo".to_string()
+        );
+    }
+
+    #[test]
+    fn args_last() {
+        assert_eq!(
+            thread::thread!("L" last in "hello".replace("l")),
+            "heLLo".to_string()
+        );
+    }
+
+    #[test]
+    fn args_as() {
+        assert_eq!(
+            thread::thread!(let s = "l" in "hello".replace(s, "L"), .len()),
+            5
+        );
+    }
+}
+
+#[cfg(test)]
+mod fallible {
+    use super::half;
+
+    fn parse(s: &str) -> Result<i32, std::num::ParseIntError> {
+        s.parse()
+    }
+
+    #[test]
+    fn some_and_then() {
+        assert_eq!(
+            thread::thread!(Some(Some(8)) in half?, half?, _ + 1),
+            Some(3)
+        );
+        assert_eq!(thread::thread!(Some(Some(6)) in half?, half?, _ + 1), None);
+        assert_eq!(
+            thread::thread!(Some(Some("8")) in .parse::<i32>().ok()?, half?),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn ok_and_then() {
+        assert_eq!(thread::thread!(Ok(Ok("8")) in parse?, _ * 2), Ok(16));
+        assert!(thread::thread!(Ok(Ok("eight")) in parse?, _ * 2).is_err());
+    }
+
+    #[test]
+    fn some_as() {
+        assert_eq!(
+            thread::thread!(let Some(v) = Some(8) in half(v)?, _ + 1),
+            Some(5)
+        );
+    }
+
+    #[test]
+    fn no_map() {
+        fn run(v: i32) -> Option<i32> {
+            Some(thread::thread!(v in half?, half?, _ + 1))
+        }
+
+        assert_eq!(run(8), Some(3));
+        assert_eq!(run(6), None);
+    }
+}
+
+#[cfg(test)]
+mod tap {
+    use std::cell::RefCell;
+
+    #[test]
+    fn plain() {
+        let seen = RefCell::new(Vec::new());
+        let log = |v: &i32| seen.borrow_mut().push(*v);
+        assert_eq!(thread::thread!(1 in _ + 1, tap log, _ * 10, tap log), 20);
+        assert_eq!(*seen.borrow(), [2, 20]);
+    }
+
+    #[test]
+    fn closure() {
+        let seen = RefCell::new(Vec::new());
+        assert_eq!(
+            thread::thread!(String::from("a") in tap |s: &String| seen.borrow_mut().push(s.len()), .len()),
+            1
+        );
+        assert_eq!(*seen.borrow(), [1]);
+    }
+
+    #[test]
+    fn placement() {
+        let seen = RefCell::new(Vec::new());
+        let log = |tag: &str, v: &i32| seen.borrow_mut().push(format!("{tag}{v}"));
+        assert_eq!(thread::thread!(1 last in tap log("v"), _ + 1), 2);
+        assert_eq!(thread::thread!(1 in tap last: log("w"), _ + 1), 2);
+        assert_eq!(*seen.borrow(), ["v1", "w1"]);
+    }
+
+    #[test]
+    fn placeholder() {
+        let seen = RefCell::new(Vec::new());
+        assert_eq!(
+            thread::thread!(vec![1, 2] in tap seen.borrow_mut().push(_.len()), .len()),
+            2
+        );
+        assert_eq!(*seen.borrow(), [2]);
+    }
+
+    #[test]
+    fn is_a_function_otherwise() {
+        fn tap(v: i32) -> i32 {
+            v + 1
+        }
+        assert_eq!(thread::thread!(1 in tap, tap(), tap), 4);
+    }
+
+    #[test]
+    fn alias() {
+        let seen = RefCell::new(Vec::new());
+        let log = |v: &i32| seen.borrow_mut().push(*v);
+        assert_eq!(
+            thread::thread!(let v = 1 in _ + 1, tap log(&v), tap log, _ * 10),
+            20
+        );
+        assert_eq!(*seen.borrow(), [2, 2]);
+    }
+
+    #[test]
+    fn some() {
+        let seen = RefCell::new(Vec::new());
+        let log = |v: &i32| seen.borrow_mut().push(*v);
+        assert_eq!(thread::thread!(Some(Some(1)) in _ + 1, tap log), Some(2));
+        assert_eq!(thread::thread!(Some(None::<i32>) in _ + 1, tap log), None);
+        assert_eq!(*seen.borrow(), [2]);
+    }
+
+    #[test]
+    fn ok() {
+        let seen = RefCell::new(Vec::new());
+        let log = |v: &i32| seen.borrow_mut().push(*v);
+        assert_eq!(
+            thread::thread!(Ok(Ok::<i32, ()>(1)) in tap log, _ + 1),
+            Ok(2)
+        );
+        assert_eq!(*seen.borrow(), [1]);
+    }
+
+    #[test]
+    fn cond() {
+        let seen = RefCell::new(Vec::new());
+        let log = |v: &i32| seen.borrow_mut().push(*v);
+        assert_eq!(
+            thread::thread!(Cond 1 in true => tap log, false => tap log, true => _ + 1),
+            2
+        );
+        assert_eq!(*seen.borrow(), [1]);
+    }
+
+    #[test]
+    fn try_magic() {
+        fn check(v: &i32) -> Result<(), String> {
+            if *v > 0 {
+                Ok(())
+            } else {
+                Err(format!("{v} is not positive"))
+            }
+        }
+        fn run(v: i32) -> Result<i32, String> {
+            Ok(thread::thread!(Try v in tap check, .checked_mul(2).ok_or(String::new())))
+        }
+
+        assert_eq!(run(2), Ok(4));
+        assert_eq!(run(0), Err("0 is not positive".to_string()));
+    }
+}
+
+#[cfg(test)]
+mod awaits {
+    use std::{
+        future::Future,
+        task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    };
+
+    /// Run a future which never actually waits.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        fn raw() -> RawWaker {
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(|_| raw(), |_| {}, |_| {}, |_| {});
+
+        let waker = unsafe { Waker::from_raw(raw()) };
+        let mut future = Box::pin(future);
+        loop {
+            if let Poll::Ready(v) = future.as_mut().poll(&mut Context::from_waker(&waker)) {
+                return v;
+            }
+        }
+    }
+
+    async fn add(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    async fn checked_half(v: i32) -> Result<i32, String> {
+        if v % 2 == 0 {
+            Ok(v / 2)
+        } else {
+            Err(format!("{v} is odd"))
+        }
+    }
+
+    struct Client;
+
+    impl Client {
+        async fn double(&self, v: i32) -> i32 {
+            v * 2
+        }
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(
+            block_on(async { thread::thread!(1 in add(1).await, add(10).await) }),
+            12
+        );
+        assert_eq!(
+            block_on(async { thread::thread!(1 last in add(1).await, _ * 2) }),
+            4
+        );
+    }
+
+    #[test]
+    fn method() {
+        let client = Client;
+        assert_eq!(
+            block_on(async { thread::thread!(1 in client.double().await) }),
+            2
+        );
+    }
+
+    #[test]
+    fn as_magic() {
+        assert_eq!(
+            block_on(async { thread::thread!(let v = 1 in add(v, 1).await, add(v, v).await) }),
+            4
+        );
+    }
+
+    #[test]
+    fn some() {
+        assert_eq!(
+            block_on(async { thread::thread!(Some(Some(1)) in add(1).await, _ * 2) }),
+            Some(4)
+        );
+        assert_eq!(
+            block_on(async { thread::thread!(Some(None) in add(1).await) }),
+            None
+        );
+    }
+
+    #[test]
+    fn ok() {
+        assert_eq!(
+            block_on(async { thread::thread!(Ok(Ok(8)) in checked_half.await?, add(1).await) }),
+            Ok(5)
+        );
+        assert_eq!(
+            block_on(async {
+                thread::thread!(Ok(Ok(6)) in checked_half.await?, checked_half.await?)
+            }),
+            Err("3 is odd".to_string())
+        );
+    }
+
+    #[test]
+    fn err() {
+        assert_eq!(
+            block_on(async { thread::thread!(Err(Err::<(), _>(1)) in add(1).await) }),
+            Err(2)
+        );
+    }
+
+    #[test]
+    fn try_magic() {
+        async fn run(v: i32) -> Result<i32, String> {
+            Ok(thread::thread!(Try v in checked_half.await, checked_half.await))
+        }
+
+        assert_eq!(block_on(run(8)), Ok(2));
+        assert_eq!(block_on(run(6)), Err("3 is odd".to_string()));
+    }
+
+    #[test]
+    fn receiver() {
+        assert_eq!(
+            block_on(async { thread::thread!(Some(Some(async { 2 })) in .await, add(1).await) }),
+            Some(3)
+        );
+    }
+}
+
+#[cfg(test)]
+mod destructuring {
+    #[derive(Debug, PartialEq)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    fn swap<T>(a: T, b: T) -> (T, T) {
+        (b, a)
+    }
+
+    #[test]
+    fn tuple() {
+        assert_eq!(
+            thread::thread!(let (a, b) = (1, 2) in swap(a, b), (a * 10, b)),
+            (20, 1)
+        );
+        let strings = (String::from("a"), String::from("b"));
+        assert_eq!(
+            thread::thread!(let (a, b) = strings in swap(a, b), (a + "!", b)),
+            ("b!".to_string(), "a".to_string())
+        );
+    }
+
+    #[test]
+    fn struct_and_slice() {
+        assert_eq!(
+            thread::thread!(let Point { x, y: z } = Point { x: 1, y: 2 } in
+                            Point { x: z, y: x }, Point { x: x + z, y: 0 }),
+            Point { x: 3, y: 0 }
+        );
+        assert_eq!(
+            thread::thread!(let [a, b] = [1, 2] in [b, a], [_[0] * 10, b]),
+            [20, 1]
+        );
+    }
+
+    #[test]
+    fn tuple_structs_close_to_keywords() {
+        #[derive(Debug, PartialEq)]
+        struct Ty(i32);
+        #[derive(Debug, PartialEq)]
+        struct Home(i32);
+        assert_eq!(thread::thread!(let Ty(v) = Ty(1) in Ty(v + 1)), Ty(2));
+        assert_eq!(
+            thread::thread!(let Home(v) = Home(1) in Home(v * 3)),
+            Home(3)
+        );
+    }
+
+    #[test]
+    fn placement_and_tap() {
+        let mut seen = Vec::new();
+        fn sum((a, b): (i32, i32)) -> i32 {
+            a + b
+        }
+        assert_eq!(
+            thread::thread!(let (a, b) = (1, 2) in tap |p: &(i32, i32)| seen.push(*p), (a, b + 1), (sum(_), 0)),
+            (4, 0)
+        );
+        assert_eq!(seen, [(1, 2)]);
+    }
+
+    #[test]
+    fn some() {
+        let entry = Some(("k", 1));
+        assert_eq!(
+            thread::thread!(let Some((k, v)) = entry in (k, v + 1), format!("{k}{v}")),
+            Some("k2".to_string())
+        );
+        assert_eq!(
+            thread::thread!(let Ok((k, v)) = Ok::<_, ()>(("k", 1)) in (v, k)),
+            Ok((1, "k"))
+        );
+    }
+
+    #[test]
+    fn cond() {
+        assert_eq!(
+            thread::thread!(let Cond((a, b)) = (1, 2) in a < b => swap(a, b), a < b => swap(a, b)),
+            (2, 1)
+        );
+        assert_eq!(
+            thread::thread!(let CondFirst((a, b)) = (1, 2) in a > b => swap(a, b), else => (a, 0)),
+            (1, 0)
+        );
+    }
+
+    #[test]
+    fn try_magic() {
+        fn run(pair: (&str, &str)) -> Result<(i32, i32), std::num::ParseIntError> {
+            Ok(thread::thread!(let Try((a, b)) = pair in Ok((a.parse()?, b.parse()?))))
+        }
+        assert_eq!(run(("1", "2")), Ok((1, 2)));
+        assert!(run(("1", "b")).is_err());
+    }
+}
+
+#[cfg(test)]
+mod modifiers {
+    fn push_twice(v: &mut Vec<i32>) {
+        v.push(1);
+        v.push(1);
+    }
+
+    #[test]
+    fn mutable() {
+        assert_eq!(
+            thread::thread!(let mut acc = Vec::new() in tap push_twice(&mut acc), tap push_twice(&mut acc), .len()),
+            4
+        );
+        assert_eq!(
+            thread::thread!(let mut v = vec![3, 1, 2] in { v.sort(); v }, tap |v: &Vec<i32>| assert_eq!(v, &[1, 2, 3]), _),
+            [1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn reference() {
+        let big = vec![1, 2, 3];
+        let first = thread::thread!(let ref v = big in v.first(), Option::unwrap);
+        assert_eq!(first, &1);
+        assert_eq!(big.len(), 3);
+    }
+
+    #[test]
+    fn mutable_reference() {
+        let mut big = vec![1, 2, 3];
+        thread::thread!(let ref mut v = big in { v.push(4); v }, tap |v: &&mut Vec<i32>| assert_eq!(v.len(), 4));
+        assert_eq!(big.len(), 4);
+    }
+
+    #[test]
+    fn destructuring() {
+        let pair = (vec![1], 2);
+        let (a, b) = thread::thread!(let (mut a, ref b) = pair in { a.push(*b); (a, b) });
+        assert_eq!((a, b), (vec![1, 2], &2));
+    }
+
+    #[test]
+    fn some() {
+        assert_eq!(
+            thread::thread!(let Some(mut v) = Some(vec![2, 1]) in { v.sort(); v }, .len()),
+            Some(2)
+        );
+        assert_eq!(
+            thread::thread!(let Ok(ref s) = Ok::<_, ()>(String::from("a")) in s.len()),
+            Ok(1)
+        );
+    }
+}
+
+#[cfg(test)]
+mod bindings {
+    use super::add;
+
+    #[test]
+    fn placement() {
+        assert_eq!(thread::thread!(2 in add(1) as three, add(three)), 6);
+        assert_eq!(
+            thread::thread!(" a,b " in .trim() as trimmed, .split(','), Iterator::count, |n| (n, trimmed)),
+            (2, "a,b")
+        );
+        assert_eq!(thread::thread!(1 in add(1) as two, _ + two as u8 as i32), 4);
+        assert_eq!(
+            thread::thread!(1 in add(1), |v| v * 2 as doubled, add(doubled)),
+            8
+        );
+    }
+
+    #[test]
+    fn casts_are_kept() {
+        #[allow(non_camel_case_types)]
+        type c_int = i32;
+        let x: i64 = 3;
+        let y: c_int = thread::thread!(x in (_ as c_int), _ + 1);
+        assert_eq!(y, 4);
+        assert_eq!(thread::thread!(x in (_ as c_int) as n, add(n)), 6);
+        assert_eq!(thread::thread!(x in _ as u8, u32::from), 3);
+    }
+
+    #[test]
+    fn alias() {
+        assert_eq!(thread::thread!(let v = 2 in v * 3 as six, v + six), 12);
+        assert_eq!(
+            thread::thread!(let (a, b) = (1, 2) in (b, a) as swapped, (a + swapped.0, b)),
+            (4, 1)
+        );
+    }
+
+    #[test]
+    fn try_magic() {
+        fn run(s: &str) -> Result<i32, std::num::ParseIntError> {
+            Ok(
+                thread::thread!(Try s in str::parse::<i32> as n, |v| Ok::<_, std::num::ParseIntError>(v + n)),
+            )
+        }
+        assert_eq!(run("7"), Ok(14));
+        assert!(run("a").is_err());
+    }
+
+    #[test]
+    fn cond() {
+        assert_eq!(
+            thread::thread!(Cond(1) in true => add(1) as two, false => add(10) as still_two, true => add(two + still_two)),
+            6
+        );
+    }
+}
+
+/// Clojure's arrows with forms rather than comma separated steps.
+#[cfg(test)]
+mod clojure {
+    use super::{add, half, sub};
+
+    #[test]
+    fn thread_first() {
+        assert_eq!(thread::thread!(-> 5 (sub 1) (add 10)), 14);
+        assert_eq!(thread::thread!(-> "a" str::to_uppercase (.repeat 2)), "AA");
+        assert_eq!(thread::thread!(-> [3 1 2] (.len)), 3);
+    }
+
+    #[test]
+    fn thread_last() {
+        assert_eq!(thread::thread!(->> 5 (sub 1) (add 10)), 6);
+        assert_eq!(
+            thread::thread!(->> (vec! 1 2 3) (.into_iter) (.sum::<i32>)),
+            6
+        );
+    }
+
+    #[test]
+    fn as_arrow() {
+        assert_eq!(thread::thread!(as-> 5 $ (sub 1 $) (add $ 10)), 6);
+        assert_eq!(thread::thread!(as-> 5 v (sub v 1) {v * 2}), 8);
+        assert_eq!(thread::thread!(as-> 2 $ {$ * 3} (add $ -1)), 5);
+    }
+
+    #[test]
+    fn some_arrow() {
+        assert_eq!(
+            thread::thread!(some-> (Some 8) half? half? (add 1)),
+            Some(3)
+        );
+        assert_eq!(thread::thread!(some-> (Some 6) half? half?), None);
+        assert_eq!(thread::thread!(some->> (Some 1) (sub 10)), Some(9));
+    }
+
+    #[test]
+    fn cond_arrow() {
+        assert_eq!(
+            thread::thread!(cond-> 1 true (add 1) false (add 10) true (add 100)),
+            102
+        );
+        assert_eq!(thread::thread!(cond->> 1 true (sub 10)), 9);
+        assert_eq!(thread::thread!(cond-> 4 {*_ > 3} (sub 1)), 3);
+    }
+
+    #[test]
+    fn placeholders_and_nesting() {
+        assert_eq!(thread::thread!(-> 2 (add (sub 10 _) 1)), 9);
+        assert_eq!(thread::thread!(-> "a,b" (.split ',') (Iterator::count)), 2);
+        assert_eq!(thread::thread!(-> 2 {|v| v * 3} (format! "{}" _)), "6");
+    }
+}
+
+/// The pipe operators `|>` and `|>>` rather than `in` and commas.
+#[cfg(test)]
+mod pipes {
+    use super::{half, sub};
+
+    fn as_is(v: i32) -> i32 {
+        v
+    }
+
+    #[test]
+    fn first_and_last() {
+        assert_eq!(thread::thread!(10 |> sub(1) |> sub(2)), 7);
+        assert_eq!(thread::thread!(10 |>> sub(1) |> sub(2)), -11);
+        assert_eq!(thread::thread!(" a " |> .trim() |> str::to_uppercase), "A");
+        assert_eq!(thread::thread!(2 |> |v| v * 3 |> _ + 1), 7);
+    }
+
+    #[test]
+    fn pipes_place_the_steps() {
+        assert_eq!(thread::thread!(10 |> sub(1) |>> sub(1)), -8);
+        let bounds = Bounds { first: 1, last: 3 };
+        assert_eq!(thread::thread!(bounds.last |> sub(1)), 2);
+        assert_eq!(thread::thread!(bounds.first |>> sub(bounds.last)), 2);
+    }
+
+    struct Bounds {
+        first: i32,
+        last: i32,
+    }
+
+    #[test]
+    fn alias_in_steps() {
+        assert_eq!(thread::thread!(let v = 10 |> sub(v, 1)), 9);
+        assert_eq!(
+            thread::thread!(let v = 10 |> sub(1) |>> sub(v, 2) |> sub(3)),
+            4
+        );
+        assert_eq!(
+            thread::thread!(let (a, b) = (1, 2) |> (b, a) |> |(x, y)| (x * 10, y)),
+            (20, 1)
+        );
+        assert_eq!(thread::pipe_fn!(let v: i32 |> sub(v, 1) |>> sub(1))(10), -8);
+    }
+
+    #[test]
+    fn placement_names_in_preamble() {
+        let (first, last) = (10, 20);
+        assert_eq!(thread::thread!(let v = first |> sub(1)), 9);
+        assert_eq!(thread::thread!(1 + last |> sub(1)), 20);
+        assert_eq!(thread::thread!(&first |> |v: &i32| *v), 10);
+        assert_eq!(thread::thread!(last |>> sub(1)), -19);
+    }
+
+    #[test]
+    fn commas_in_steps() {
+        assert_eq!(
+            thread::thread!(vec![1, 2] |> .into_iter() |> Iterator::collect::<Vec<i32>>),
+            [1, 2]
+        );
+        assert_eq!(thread::thread!(1 |> Ok::<i32, ()> |> Result::ok), Some(1));
+        assert_eq!(
+            thread::thread!((Ok(1)) |> |v: Result<i32, ()>| v.map_or(0, as_is) |> sub(1)),
+            0
+        );
+    }
+
+    #[test]
+    fn patterns() {
+        assert_eq!(thread::thread!(Some(Some(8)) |> half? |>> sub(1)), Some(-3));
+        assert_eq!(thread::thread!(Ok(Ok::<_, ()>(1)) |> sub(3)), Ok(-2));
+        assert_eq!(
+            thread::thread!(Cond(1) |> true => sub(3) |> false => sub(5)),
+            -2
+        );
+        assert_eq!(
+            thread::thread!(CondFirst(4) |> *_ > 3 => sub(3) |> else => sub(5)),
+            1
+        );
+    }
+
+    #[test]
+    fn steps_in_delimiters() {
+        assert_eq!(thread::thread!(3 |> { |v: i32| v | 1 } |> sub(1)), 2);
+        assert_eq!(thread::thread!(3 |> sub((1 | 4) - 4) |> as_is(_)), 2);
+    }
+}
+
+/// The `pipe!` expressions of the items with the `pipes` attribute.
+#[cfg(test)]
+mod pipe_attribute {
+    use super::sub;
+
+    #[thread::pipes]
+    fn pipelines(v: i32) -> (i32, i32, Option<i32>) {
+        let first = pipe!(v |> sub(1) |>> sub(1));
+        pipe!(first in sub(1));
+        let nested = pipe!(v |> sub(pipe!(v |> sub(1))));
+        (first, nested, pipe!(Some(Some(v)) |> sub(2)))
+    }
+
+    #[test]
+    fn function() {
+        assert_eq!(pipelines(10), (-8, 1, Some(8)));
+    }
+
+    struct Counter(i32);
+
+    #[thread::pipes]
+    impl Counter {
+        fn next(&self) -> i32 {
+            pipe!(self.0 |> _ + 1)
+        }
+    }
+
+    #[test]
+    fn impl_block() {
+        assert_eq!(Counter(1).next(), 2);
+    }
+
+    #[thread::pipes]
+    fn closures() -> i32 {
+        let add = |v: i32| pipe!(v |> _ + 1);
+        add(1)
+    }
+
+    #[test]
+    fn closure() {
+        assert_eq!(closures(), 2);
+    }
+
+    #[thread::pipes]
+    fn in_macros(v: i32) -> (String, Vec<i32>) {
+        assert_eq!(pipe!(v |> sub(1)), v - 1);
+        println!("{}", pipe!(v |>> sub(1)));
+        let text = format!("{} {w}", pipe!(v |> sub(1)), w = pipe!(v |>> sub(1)));
+        (text, vec![pipe!(v |> sub(1)), vec![pipe!(v |> sub(2))][0]])
+    }
+
+    #[test]
+    fn macro_arguments() {
+        assert_eq!(in_macros(10), ("9 -9".to_string(), vec![9, 8]));
+    }
+}
+
+/// Closures of pipelines, taking the value as their argument.
+#[cfg(test)]
+mod pipe_fn {
+    use super::{half, sub};
+
+    #[test]
+    fn placements() {
+        let f = thread::pipe_fn!(in sub(1), sub(2));
+        assert_eq!(f(10), 7);
+        let f = thread::pipe_fn!(_: i32 last in sub(1));
+        assert_eq!(f(10), -9);
+        let f = thread::pipe_fn!(_: &str in .trim(), str::len);
+        assert_eq!([" a ", "bc"].map(f), [1, 2]);
+    }
+
+    #[test]
+    fn alias() {
+        let f = thread::pipe_fn!(let v: i32 in sub(v, 1), sub(1, v));
+        assert_eq!(f(10), -8);
+        let swap = thread::pipe_fn!(let (a, b): (i32, i32) in (b, a));
+        assert_eq!(swap((1, 2)), (2, 1));
+        let f = thread::pipe_fn!(let Some(v): Option<i32> in v + 1);
+        assert_eq!(f(Some(1)), Some(2));
+    }
+
+    #[test]
+    fn patterns() {
+        let f = thread::pipe_fn!(Some(_: Option<i32>) in half?, sub(1));
+        assert_eq!((f(Some(4)), f(Some(3)), f(None)), (Some(1), None, None));
+        let f = thread::pipe_fn!(Ok(_: Result<i32, ()>) last in sub(1));
+        assert_eq!(f(Ok(3)), Ok(-2));
+        let f = thread::pipe_fn!(Cond(_: i32) in *_ > 3 => sub(3), *_ < 0 => sub(5));
+        assert_eq!((f(4), f(2)), (1, 2));
+        let f = thread::pipe_fn!(CondFirst in *_ > 3 => sub(3), else => sub(5));
+        assert_eq!((f(4), f(2)), (1, -3));
+    }
+
+    #[test]
+    fn fallible_steps() {
+        fn run(values: [&str; 2]) -> Result<Vec<i32>, std::num::ParseIntError> {
+            let parse = thread::pipe_fn!(_: &str in str::parse::<i32>?, Ok);
+            values.into_iter().map(parse).collect()
+        }
+        assert_eq!(run(["1", "2"]), Ok(vec![1, 2]));
+        assert!(run(["1", "b"]).is_err());
+    }
+
+    #[test]
+    fn pipes_and_captures() {
+        let offset = 3;
+        let f = thread::pipe_fn!(_: i32 |> sub(offset) |>> sub(1));
+        assert_eq!(f(10), -6);
+        assert_eq!(f(4), 0);
+    }
+}
+
+#[cfg(test)]
+mod step_kinds {
+    struct Ops {
+        double: fn(i32) -> i32,
+    }
+
+    fn triple(v: i32) -> i32 {
+        v * 3
+    }
+
+    #[test]
+    fn callables() {
+        let fns: [fn(i32) -> i32; 1] = [triple];
+        let ops = Ops { double: |v| v * 2 };
+        assert_eq!(thread::thread!(1 in fns[0], (ops.double), (triple)), 18);
+        assert_eq!(
+            thread::thread!(1 in match true { true => triple, false => |v| v }),
+            3
+        );
+    }
+
+    #[test]
+    fn operators_with_placeholders() {
+        assert_eq!(thread::thread!(1 in _ + 1, -_, _ as i64, -_ as u8), 2);
+        assert_eq!(thread::thread!(2 in 0.._, .len()), 2);
+    }
+
+    #[test]
+    fn expressions_with_placeholders() {
+        assert_eq!(thread::thread!(1 in [_, 2], (_[0], _[1]), _.1), 2);
+    }
+}
+
+/// User variables named like the bindings of the generated code must not be captured by them.
+#[cfg(test)]
+mod hygiene {
+    use super::add;
+
+    #[test]
+    fn map_closure() {
+        let i = 10;
+        assert_eq!(thread::thread!(Some(Some(1)) in add(i), add(i)), Some(21));
+        assert_eq!(thread::thread!(Ok(Ok::<_, ()>(1)) in add(i)), Ok(11));
+        assert_eq!(thread::thread!(Err(Err::<(), _>(1)) in add(i)), Err(11));
+        let value = 10;
+        assert_eq!(
+            thread::thread!(Some(Some(1)) in add(value), _ + value),
+            Some(21)
+        );
+    }
+
+    #[test]
+    fn internal_names() {
+        let (value, receiver, reference, placeholder, tapped, other) = (1, 2, 3, 4, 5, 6);
+        let names = value + receiver + reference + placeholder + tapped + other;
+        let sum = |v: i32| {
+            thread::thread!(v in
+                            add(value), add(receiver), add(reference),
+                            _ + placeholder, tap |_: &i32| tapped, add(other))
+        };
+        assert_eq!(sum(0), names - tapped);
+
+        let some = |v: i32| {
+            thread::thread!(Some(Some(v)) in
+                            add(value), add(receiver), add(reference),
+                            _ + placeholder, tap |_: &i32| tapped, add(other))
+        };
+        assert_eq!(some(0), Some(names - tapped));
+
+        let try_magic = |v: i32| -> Option<i32> {
+            Some(thread::thread!(Try v in
+                                 .checked_add(value), .checked_add(receiver),
+                                 .checked_add(reference), .checked_add(placeholder),
+                                 .checked_add(other)))
+        };
+        assert_eq!(try_magic(0), Some(names - tapped));
+
+        let cond = |v: i32| {
+            thread::thread!(Cond v in
+                            *_ >= value => add(value), *_ >= reference => add(receiver),
+                            *_ < placeholder => _ * other)
+        };
+        assert_eq!(cond(1), 12);
+
+        let cond_first = |v: i32| thread::thread!(CondFirst v in *_ == value => add(receiver), else => add(reference));
+        assert_eq!(cond_first(1), 3);
+        assert_eq!(cond_first(0), 3);
+    }
+
+    #[test]
+    fn alias_named_like_internal() {
+        assert_eq!(
+            thread::thread!(let value = 1 in add(value, 1), add(2, value)),
+            4
+        );
+        assert_eq!(
+            thread::thread!(let Some(i) = Some(1) in add(i, 1), _ * 2),
+            Some(4)
+        );
+        assert_eq!(
+            thread::thread!(let Cond(reference) = 1 in true => add(reference, 1)),
+            2
+        );
+    }
+}
+
+#[cfg(test)]
+mod long_pipeline {
+    fn inc(v: u32) -> u32 {
+        v + 1
+    }
+
+    #[test]
+    fn two_hundred_steps() {
+        assert_eq!(
+            thread::thread!(0 in
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+                            inc, inc, inc, inc, inc, inc, inc, inc, inc, inc,
+            ),
+            200
+        );
+    }
+
+    #[test]
+    fn two_hundred_steps_as() {
+        assert_eq!(
+            thread::thread!(let v = 0 in
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+                            inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc(v), inc,
+            ),
+            200
+        );
+    }
+}
+
+/// The temporaries of the initial value live through the pipeline.
+#[cfg(test)]
+mod borrowed_initial_value {
+    #[test]
+    fn plain() {
+        assert_eq!(thread::thread!(String::from("a b").as_str() in str::len), 3);
+        assert_eq!(
+            thread::thread!(String::from("ab").chars() in Iterator::count),
+            2
+        );
+        assert_eq!(
+            thread::thread!(let s = String::from("a").as_str() in str::len(s)),
+            1
+        );
+    }
+
+    #[test]
+    fn patterns() {
+        assert_eq!(
+            thread::thread!(Some(String::from("ab").as_str().strip_prefix('a')) in str::len),
+            Some(1)
+        );
+        assert_eq!(
+            thread::thread!(Cond String::from("ab").as_str() in true => str::trim),
+            "ab"
+        );
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,7 @@
+fn main() {
+    let v = 1;
+    let _ = thread::thread!(v in 1);
+    let _ = thread::thread!(v in x = 2);
+    let _ = thread::thread!(v in for i in 0..3 {});
+    let _ = thread::thread!(v in (1, 2));
+}
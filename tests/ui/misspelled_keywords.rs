@@ -0,0 +1,8 @@
+fn main() {
+    let x = 1;
+    let _ = thread::thread!(x frist in std::ops::Add::add(1));
+    let _ = thread::thread!(x nht(1) in std::ops::Add::add(1));
+    let _ = thread::thread!(Tyr x in std::ops::Add::add(1));
+    let _ = thread::thread!(Cnod 1 in true => std::ops::Add::add(1));
+    let _ = thread::thread!(let Som v = Some(x) in v);
+}
@@ -0,0 +1,6 @@
+fn main() {
+    let v = 1;
+    let _ = thread::thread!(v in -v);
+    let _ = thread::thread!(v in v + 1);
+    let _ = thread::thread!(v in !v);
+}
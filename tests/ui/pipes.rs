@@ -0,0 +1,6 @@
+fn main() {
+    let _ = thread::thread!(10 |> std::ops::Sub::sub(1), std::ops::Sub::sub(2));
+    let _ = thread::thread!(10 last |> std::ops::Sub::sub(1));
+    let _ = thread::thread!(10 |> last: std::ops::Sub::sub(1));
+    let _ = thread::thread!(10 |> std::ops::Sub::sub(1) |>);
+}
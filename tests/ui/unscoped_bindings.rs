@@ -0,0 +1,4 @@
+fn main() {
+    let _ = thread::thread!(Some(Some(1)) in Option::Some as x, |v: i32| v + x);
+    let _ = thread::thread!(CondFirst 1 in true => _ + 1 as x, else => _ + 2);
+}
@@ -0,0 +1,4 @@
+fn main() {
+    let v = 1;
+    let _ = thread::thread!(v in &raw const v);
+}